@@ -12,11 +12,12 @@
 
 use ffi_support::FfiStr;
 use std::{
+    cell::RefCell,
     num::NonZeroUsize,
     ptr::null_mut,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Mutex, OnceLock,
+        LockResult, Mutex, OnceLock, PoisonError,
     },
 };
 pub use winmmf::Namespace as ValidNamespaces;
@@ -30,6 +31,25 @@ static MMFS: OnceLock<MMFWrapper> = OnceLock::new();
 /// Currently selected default MMF to operate on. Counting starts from 1.
 static CURRENT: AtomicUsize = AtomicUsize::new(0);
 
+thread_local! {
+    /// The last error produced by a call on this thread, in human-readable form.
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Store an error for [`last_error_string`] and pass it on, for use in `map_err` and friends.
+fn set_last_error(err: Error) -> Error {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(err.to_string()));
+    err
+}
+
+/// Take the list of MMFs back from a poisoned lock.
+///
+/// A panic while the lock was held doesn't leave the list in an invalid state, it's just a `Vec` of handles. Refusing
+/// to touch it ever again would only brick every following call.
+fn recover_poison<T>(poisoned: PoisonError<T>) -> LockResult<T> {
+    Ok(poisoned.into_inner())
+}
+
 /// Lazy wrapper to use when ensuring initialization
 fn _init<'a>(cap: usize) -> MMFWrapper<'a> {
     Mutex::new(Vec::with_capacity(cap))
@@ -62,9 +82,10 @@ pub extern "system" fn open(size: Option<NonZeroUsize>, name: FfiStr, namespace:
         (_, None, _) => -2,
         (_, _, Err(_)) => -3,
         (Some(size), Some(namestr), Ok(ns)) => {
-            if let Ok(mapped) = MemoryMappedFile::open(size, namestr, ns, false) {
+            if let Ok(mapped) = MemoryMappedFile::open(size, namestr, ns, false).map_err(set_last_error) {
                 MMFS.get_or_init(|| _init(1))
                     .lock()
+                    .or_else(recover_poison)
                     .map(|mut inner| {
                         inner.push(mapped);
                         let idx = inner.len() - 1;
@@ -96,9 +117,10 @@ pub extern "system" fn new(size: Option<NonZeroUsize>, name: FfiStr, namespace:
         (_, None, _) => -2,
         (_, _, Err(_)) => -3,
         (Some(size), Some(namestr), Ok(ns)) => {
-            if let Ok(mapped) = MemoryMappedFile::new(size, namestr, ns) {
+            if let Ok(mapped) = MemoryMappedFile::new(size, namestr, ns).map_err(set_last_error) {
                 MMFS.get_or_init(|| _init(1))
                     .lock()
+                    .or_else(recover_poison)
                     .map(|mut inner| {
                         inner.push(mapped);
                         let idx = inner.len() - 1;
@@ -113,6 +135,68 @@ pub extern "system" fn new(size: Option<NonZeroUsize>, name: FfiStr, namespace:
     }
 }
 
+/// Open an MMF if it exists, or create it if it doesn't, and push it into the list.
+///
+/// This goes through [`MemoryMappedFile::open_or_create`], which asks the OS to create the MMF and attaches to it
+/// instead if it already exists, all in one call. There is no window between checking and creating for another process
+/// to slip into, and an existing MMF is never zeroed. Return values are the same as for [`new`], plus:
+///
+/// - -6: The name refers to something that couldn't be found, like a namespace that doesn't exist
+/// - -7: Access to the MMF was denied
+/// - -8: Either the MMF exists but is smaller than `size`, or the OS ran out of memory or resources to create or map it
+#[no_mangle]
+pub extern "system" fn new_or_open(size: Option<NonZeroUsize>, name: FfiStr, namespace: u8) -> isize {
+    match (size, name.as_opt_str(), namespace.try_into()) {
+        (None, _, _) => -1,
+        (_, None, _) => -2,
+        (_, _, Err(_)) => -3,
+        (Some(size), Some(namestr), Ok(ns)) => {
+            match MemoryMappedFile::open_or_create(size, namestr, ns).map_err(set_last_error) {
+                Ok(mapped) => MMFS
+                    .get_or_init(|| _init(1))
+                    .lock()
+                    .or_else(recover_poison)
+                    .map(|mut inner| {
+                        inner.push(mapped);
+                        let idx = inner.len() - 1;
+                        _ = CURRENT.compare_exchange(0, idx, Ordering::Acquire, Ordering::Relaxed);
+                        idx as isize
+                    })
+                    .unwrap_or(-5),
+                Err(Error::MMF_NotFound) => -6,
+                Err(Error::AccessDenied) => -7,
+                Err(Error::NotEnoughMemory) => -8,
+                Err(_) => -4,
+            }
+        }
+    }
+}
+
+/// Get the size of the data portion of an MMF, so you know how large your buffers need to be.
+///
+/// Return values are the size for success, or a negative integer for errors:
+///
+/// - -1: No MMFs opened yet, or none at this index
+/// - -2: MMF is closed
+/// - -3: ???
+#[no_mangle]
+pub extern "system" fn mmf_size(mmf_idx: Option<NonZeroUsize>) -> isize {
+    MMFS.get()
+        .map(|inner| {
+            inner
+                .lock()
+                .or_else(recover_poison)
+                .map(|inner| {
+                    inner
+                        .get(mmf_idx.map(|nsu| nsu.get()).unwrap_or_else(|| CURRENT.load(Ordering::Acquire)))
+                        .map(|mmf| if mmf.is_closed() { -2 } else { isize::try_from(mmf.size()).unwrap_or(isize::MAX) })
+                        .unwrap_or(-1)
+                })
+                .unwrap_or(-3)
+        })
+        .unwrap_or(-1)
+}
+
 /// Read `count` bytes from the MMF into the provided buffer.
 ///
 /// It is up to the caller to ensure the buffer is large enough to hold at least `count` bytes. Passing in a buffer
@@ -139,11 +223,12 @@ pub unsafe extern "system" fn read_buf(mmf_idx: Option<NonZeroUsize>, count: usi
         .map(|inner| {
             inner
                 .lock()
+                .or_else(recover_poison)
                 .map(|inner| {
                     inner
                         .get(mmf_idx.map(|nsu| nsu.get()).unwrap_or_else(|| CURRENT.load(Ordering::Acquire)))
                         .map(|mmf| {
-                            mmf.read_to_raw(buff, count).map(|_| 0).unwrap_or_else(|e| match e {
+                            mmf.read_to_raw(buff, count).map(|_| 0).unwrap_or_else(|e| match set_last_error(e) {
                                 Error::MMF_NotFound => -2,
                                 Error::Uninitialized => -3,
                                 _ => -4,
@@ -156,6 +241,58 @@ pub unsafe extern "system" fn read_buf(mmf_idx: Option<NonZeroUsize>, count: usi
         .unwrap_or(-1)
 }
 
+/// Read up to `count` bytes from the MMF into a buffer you own and keep around, returning how many were read.
+///
+/// Unlike [`read_buf`], the amount read is clamped to both `cap` and the size of the MMF, so one buffer can be reused
+/// for the whole lifetime of your program. A `count` of 0 reads as much as fits.
+/// Return values are the amount of bytes read, or negative integers for errors.
+///
+/// - -1: No MMFs opened yet
+/// - -2: MMF is closed
+/// - -3: MMF isn't initialized
+/// - -4: ???
+/// - -5: `buff` is a null pointer
+///
+/// # Safety
+/// Ensure `buff` is valid for at least `cap` bytes and all will be well.
+#[no_mangle]
+pub unsafe extern "system" fn read_reuse(
+    mmf_idx: Option<NonZeroUsize>,
+    count: usize,
+    buff: *mut u8,
+    cap: usize,
+) -> isize {
+    if buff.is_null() {
+        return -5;
+    }
+    if cap == 0 {
+        return 0;
+    }
+    MMFS.get()
+        .map(|inner| {
+            inner
+                .lock()
+                .or_else(recover_poison)
+                .map(|inner| {
+                    inner
+                        .get(mmf_idx.map(|nsu| nsu.get()).unwrap_or_else(|| CURRENT.load(Ordering::Acquire)))
+                        .map(|mmf| {
+                            let count = if count == 0 { cap } else { count.min(cap) }.min(mmf.size());
+                            mmf.read_to_raw(buff, count).map(|read| read as isize).unwrap_or_else(|e| {
+                                match set_last_error(e) {
+                                    Error::MMF_NotFound => -2,
+                                    Error::Uninitialized => -3,
+                                    _ => -4,
+                                }
+                            })
+                        })
+                        .unwrap_or(-1)
+                })
+                .unwrap_or(-4)
+        })
+        .unwrap_or(-1)
+}
+
 /// Read `count` bytes or all contents from the MMF and give back a pointer to the data.
 ///
 /// The pointer produced from this function **must** be freed using [`free_result`], regardless of error state.
@@ -171,6 +308,7 @@ pub extern "system" fn read(mmf_idx: Option<NonZeroUsize>, count: usize) -> *mut
         .map(|inner| {
             inner
                 .lock()
+                .or_else(recover_poison)
                 .map(|inner| {
                     inner
                         .get(mmf_idx.map(|nsu| nsu.get()).unwrap_or_else(|| CURRENT.load(Ordering::Acquire)))
@@ -192,7 +330,7 @@ pub extern "system" fn read(mmf_idx: Option<NonZeroUsize>, count: usize) -> *mut
                                     } /* Becomes a pointer to the first */
                                     // element in the vec
                                     Err(e) => {
-                                        let val = match e {
+                                        let val = match set_last_error(e) {
                                             Error::MMF_NotFound => -2_i8,
                                             Error::Uninitialized => -3_i8,
                                             _ => -4_i8,
@@ -216,6 +354,70 @@ pub extern "system" fn read(mmf_idx: Option<NonZeroUsize>, count: usize) -> *mut
         .unwrap_or(null_mut())
 }
 
+/// The result of [`read_struct`], keeping errors and data apart.
+///
+/// On success, `code` is 0 and `ptr` points to `len` bytes of data. Free it with [`free_raw`], passing `len` as the
+/// size. On failure, `code` is negative, `ptr` is null and `len` is 0, so there's nothing to free.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ReadResult {
+    /// 0 on success, or one of the error codes listed for [`read_struct`]
+    pub code: i32,
+    /// The amount of bytes behind `ptr`
+    pub len: usize,
+    /// The data read, or null on failure
+    pub ptr: *mut u8,
+}
+
+/// Shorthand for the failure case, with an error code and nothing else.
+impl From<i32> for ReadResult {
+    /// Wrap an error code with a null pointer.
+    fn from(code: i32) -> Self {
+        Self { code, len: 0, ptr: null_mut() }
+    }
+}
+
+/// Read `count` bytes or all contents from the MMF, returning the data and any errors separately.
+///
+/// This is an alternative to [`read`] where errors don't have to be dug out of the data. See [`ReadResult`] for how to
+/// use the result. A `count` of 0 reads the entire MMF.
+///
+/// - 0: Success
+/// - -1: No MMFs opened yet
+/// - -2: MMF is closed
+/// - -3: MMF isn't initialized
+/// - -4: ???
+#[no_mangle]
+pub extern "system" fn read_struct(mmf_idx: Option<NonZeroUsize>, count: usize) -> ReadResult {
+    MMFS.get()
+        .map(|inner| {
+            inner
+                .lock()
+                .or_else(recover_poison)
+                .map(|inner| {
+                    inner
+                        .get(mmf_idx.map(|nsu| nsu.get()).unwrap_or_else(|| CURRENT.load(Ordering::Acquire)))
+                        .map(|mmf| match mmf.read(count) {
+                            Ok(data) => {
+                                // A boxed slice has no spare capacity, so `free_raw` with `len` gets the layout right.
+                                let len = data.len();
+                                let ptr = Box::into_raw(data.into_boxed_slice()).cast::<u8>();
+                                ReadResult { code: 0, len, ptr }
+                            }
+                            Err(e) => match set_last_error(e) {
+                                Error::MMF_NotFound => -2,
+                                Error::Uninitialized => -3,
+                                _ => -4,
+                            }
+                            .into(),
+                        })
+                        .unwrap_or_else(|| (-1).into())
+                })
+                .unwrap_or_else(|_| (-4).into())
+        })
+        .unwrap_or_else(|| (-1).into())
+}
+
 /// Free a pointer used for reading from an MMF by its index number.
 ///
 /// # Safety
@@ -230,6 +432,7 @@ pub unsafe extern "system" fn free_result(mmf_idx: Option<NonZeroUsize>, res: *m
         .map(|inner| {
             inner
                 .lock()
+                .or_else(recover_poison)
                 .map(|inner| {
                     inner
                         .get(mmf_idx.map(|nsu| nsu.get()).unwrap_or_else(|| CURRENT.load(Ordering::Acquire)))
@@ -273,12 +476,13 @@ pub unsafe extern "system" fn write(mmf_idx: Option<NonZeroUsize>, data: *mut u8
             .map(|inner| {
                 inner
                     .lock()
+                    .or_else(recover_poison)
                     .map(|inner| {
                         inner
                             .get(mmf_idx.map(|nsu| nsu.get()).unwrap_or_else(|| CURRENT.load(Ordering::Acquire)))
                             .map(|mmf| {
                                 let buff = unsafe { std::slice::from_raw_parts_mut(data, size) };
-                                match mmf.write(buff) {
+                                match mmf.write(buff).map_err(set_last_error) {
                                     Ok(_) => 0,
                                     Err(Error::MMF_NotFound) => -1,
                                     Err(Error::NotEnoughMemory) => -2,
@@ -313,9 +517,10 @@ pub extern "system" fn open_ro(size: Option<NonZeroUsize>, name: FfiStr, namespa
         (_, None, _) => null_mut(),
         (_, _, Err(_)) => null_mut(),
         (Some(size), Some(namestr), Ok(ns)) => {
-            if let Ok(mapped) = MemoryMappedFile::open(size, namestr, ns, true) {
+            if let Ok(mapped) = MemoryMappedFile::open(size, namestr, ns, true).map_err(set_last_error) {
                 MMFS.get_or_init(|| _init(1))
                     .lock()
+                    .or_else(recover_poison)
                     .map(|mut inner| {
                         inner.push(mapped);
                         let count = inner.len() - 1;
@@ -335,12 +540,62 @@ pub extern "system" fn open_ro(size: Option<NonZeroUsize>, name: FfiStr, namespa
     }
 }
 
+/// Copy the message for the last error on this thread into `buf`, as UTF-8 without a null terminator.
+///
+/// At most `cap` bytes are written, so the message may be cut off. The return value is the full length of the message,
+/// so you can call this with a null pointer and a `cap` of 0 to find out how large the buffer needs to be. A return
+/// value of 0 means no error happened on this thread yet.
+///
+/// Only failures that produce an error in WinMMF itself are recorded, bad arguments only give their return code.
+///
+/// # Safety
+/// `buf` must be valid for writing at least `cap` bytes, or null if `cap` is 0.
+#[no_mangle]
+pub unsafe extern "system" fn last_error_string(buf: *mut u8, cap: usize) -> isize {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+        let msg = last.as_deref().unwrap_or_default();
+        if !buf.is_null() {
+            unsafe { msg.as_ptr().copy_to_nonoverlapping(buf, msg.len().min(cap)) };
+        }
+        isize::try_from(msg.len()).unwrap_or(isize::MAX)
+    })
+}
+
 /// Close the MMF
 ///
 /// Closes the specific instance stored here without interferring with other processes that might be using it.
 #[no_mangle]
 pub extern "system" fn close(mmf_idx: usize) {
     MMFS.get()
-        .map(|inner| inner.lock().map(|mut inner| drop(inner.remove(mmf_idx))).unwrap_or_default())
+        .map(|inner| {
+            inner
+                .lock()
+                .or_else(recover_poison)
+                .map(|mut inner| drop(inner.remove(mmf_idx)))
+                .unwrap_or_default()
+        })
         .unwrap_or_default()
 }
+
+/// Close every MMF opened through this library, for a single teardown call on exit.
+///
+/// Every MMF is dropped, which unmaps its view and closes its handle. Afterwards, the list is empty and new MMFs can
+/// be opened as if starting fresh. Returns the number of MMFs closed, or -1 if they couldn't be reached.
+#[no_mangle]
+pub extern "system" fn shutdown() -> isize {
+    MMFS.get()
+        .map(|inner| {
+            inner
+                .lock()
+                .or_else(recover_poison)
+                .map(|mut inner| {
+                    let count = inner.len();
+                    inner.clear();
+                    CURRENT.store(0, Ordering::Release);
+                    isize::try_from(count).unwrap_or(isize::MAX)
+                })
+                .unwrap_or(-1)
+        })
+        .unwrap_or(0)
+}
@@ -0,0 +1,37 @@
+//! Compares [`Mmf::read`], which allocates a fresh Vec every call, to `read_into` reusing a single buffer.
+//!
+//! Run with `cargo bench -p winmmf`. The gap between the two is the cost of the allocation on every read.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::{hint::black_box, num::NonZeroUsize};
+use winmmf::{states::RWLock, *};
+
+/// Read the whole MMF with both methods, for a few sizes.
+fn read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+    for size in [64, 4096, 65536] {
+        let mmf = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(size).unwrap(), "bench_read", Namespace::LOCAL)
+            .expect("creation failed");
+        mmf.write(&vec![0x5A; size]).expect("Failed to write");
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("read", size), &mmf, |b, mmf| {
+            b.iter(|| black_box(mmf.read(0).expect("Failed to read")))
+        });
+        let mut buf = Vec::new();
+        group.bench_with_input(BenchmarkId::new("read_into", size), &mmf, |b, mmf| {
+            b.iter(|| {
+                mmf.read_into(&mut buf, 0).expect("Failed to read");
+                black_box(&buf);
+            })
+        });
+        // A fresh buffer for every iteration, to show what `read_into` costs before it's warmed up.
+        group.bench_with_input(BenchmarkId::new("read_into_cold", size), &mmf, |b, mmf| {
+            b.iter_batched_ref(Vec::new, |buf| mmf.read_into(buf, 0).expect("Failed to read"), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, read);
+criterion_main!(benches);
@@ -6,10 +6,19 @@
 //! or it will be closed when the program ends.
 
 use microseh::Exception;
-use std::{borrow::Cow, error::Error as stderr, fmt};
-use windows::core::{Error as WErr, HRESULT};
+use std::{borrow::Cow, error::Error as stderr, fmt, io};
+use windows::{
+    core::{Error as WErr, HRESULT},
+    Win32::Foundation::{
+        ERROR_ACCESS_DENIED, ERROR_FILE_NOT_FOUND, ERROR_NOT_ENOUGH_MEMORY, ERROR_NO_SYSTEM_RESOURCES,
+    },
+};
 
 /// Errors used with Memory-Mapped Files.
+///
+/// The discriminants are a stable contract, as they're what crosses FFI boundaries. Existing ones never change, and
+/// new variants get a number of their own. Use [`code`][Self::code] and [`from_code`][Self::from_code] to go back and
+/// forth.
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone, PartialEq)]
 #[repr(u8)]
@@ -31,6 +40,27 @@ pub enum Error {
     LockViolation = 6,
     /// Spinlocks spun for the maximum amount of tries allowed
     MaxTriesReached = 7,
+    /// The bytes read were supposed to be a string, but they're not valid UTF-8
+    InvalidUtf8 = 8,
+    /// The data could not be (de)serialized
+    Serialization = 9,
+    /// The offset into the MMF is not a multiple of the system allocation granularity
+    UnalignedOffset = 10,
+    /// You're not allowed to do that. Missing rights on the MMF, or a privilege like `SeCreateGlobalPrivilege` for the
+    /// Global namespace or `SeLockMemoryPrivilege` for large pages.
+    AccessDenied = 11,
+    /// Someone handed us a null pointer where a valid one was required
+    NullPointer = 12,
+    /// The memory behind the MMF isn't committed anymore, it can't be trusted
+    NotCommitted = 13,
+    /// Tried to release a read lock while the lock is held for writing, so there's no read lock to release
+    NotReadLocked = 14,
+    /// Tried to release a write lock that isn't held
+    NotWriteLocked = 15,
+    /// Tried to release a read lock while nobody is reading
+    NoReadersToRelease = 16,
+    /// The name of the MMF doesn't fit, namespace included. Cutting it off could make it the name of a different MMF.
+    NameTooLong = 17,
     /// No explanation, only errors
     GeneralFailure = 253,
     /// Generic OS error that we can't do much with other than catching and forwarding
@@ -39,10 +69,46 @@ pub enum Error {
     OS_OK(WErr) = 255,
 }
 
+impl Error {
+    /// Get the discriminant of this error.
+    pub fn code(&self) -> u8 {
+        // Safety: `repr(u8)` puts the discriminant in the first byte, even for variants with fields.
+        unsafe { *(self as *const Self).cast::<u8>() }
+    }
+
+    /// Get the error that has `code` for a discriminant, if there's one without an OS error attached to it.
+    ///
+    /// The OS errors can't be rebuilt from just the code, so 254 and 255 give `None`, as does anything unused.
+    pub fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            0 => Self::ReadLocked,
+            1 => Self::WriteLocked,
+            2 => Self::Uninitialized,
+            3 => Self::MaxReaders,
+            4 => Self::NotEnoughMemory,
+            5 => Self::MMF_NotFound,
+            6 => Self::LockViolation,
+            7 => Self::MaxTriesReached,
+            8 => Self::InvalidUtf8,
+            9 => Self::Serialization,
+            10 => Self::UnalignedOffset,
+            11 => Self::AccessDenied,
+            12 => Self::NullPointer,
+            13 => Self::NotCommitted,
+            14 => Self::NotReadLocked,
+            15 => Self::NotWriteLocked,
+            16 => Self::NoReadersToRelease,
+            17 => Self::NameTooLong,
+            253 => Self::GeneralFailure,
+            _ => return None,
+        })
+    }
+}
+
 impl stderr for Error {
     fn source(&self) -> Option<&(dyn stderr + 'static)> {
         match self {
-            Self::OS_Err(w) => Some(w),
+            Self::OS_Err(w) | Self::OS_OK(w) => Some(w),
             _ => None,
         }
     }
@@ -53,11 +119,18 @@ impl From<HRESULT> for Error {
         match value {
             HRESULT(30) => Self::ReadLocked,
             HRESULT(19) => Self::WriteLocked,
-            HRESULT(8) => Self::NotEnoughMemory,
+            HRESULT(8) | HRESULT(1450) => Self::NotEnoughMemory,
+            HRESULT(5) => Self::AccessDenied,
             HRESULT(9) => Self::Uninitialized,
             HRESULT(2) => Self::MMF_NotFound,
             HRESULT(33) => Self::LockViolation,
             HRESULT(0) => Self::OS_OK(value.into()),
+            // The same codes when they're wrapped into an HRESULT, which is how the `windows` crate hands them over.
+            _ if value == ERROR_NOT_ENOUGH_MEMORY.to_hresult() || value == ERROR_NO_SYSTEM_RESOURCES.to_hresult() => {
+                Self::NotEnoughMemory
+            }
+            _ if value == ERROR_ACCESS_DENIED.to_hresult() => Self::AccessDenied,
+            _ if value == ERROR_FILE_NOT_FOUND.to_hresult() => Self::MMF_NotFound,
             _ => Self::OS_Err(value.into()),
         }
     }
@@ -83,6 +156,21 @@ impl From<WErr> for Error {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        match value.kind() {
+            io::ErrorKind::NotFound => Self::MMF_NotFound,
+            io::ErrorKind::PermissionDenied => Self::AccessDenied,
+            io::ErrorKind::OutOfMemory => Self::NotEnoughMemory,
+            // Errors that came from the OS still carry their code, so keep that around.
+            _ => match value.raw_os_error() {
+                Some(code) => Self::OS_Err(WErr::from_hresult(HRESULT::from_win32(code as u32))),
+                None => Self::GeneralFailure,
+            },
+        }
+    }
+}
+
 impl From<Exception> for Error {
     fn from(value: Exception) -> Self {
         // microseh::Exception::ExceptionCode is a `repr(u32)` enum. This is safe to cast.
@@ -102,6 +190,20 @@ impl fmt::Display for Error {
             Self::Uninitialized => Cow::from("Memory Mapped File was not yet initialized"),
             Self::MaxReaders => Cow::from("The maximum amount of readers is already registered"),
             Self::MaxTriesReached => Cow::from("The maximum amount of tries was reached spinning"),
+            Self::InvalidUtf8 => Cow::from("The data read from the MMF is not valid UTF-8"),
+            Self::Serialization => Cow::from("The data could not be serialized or deserialized"),
+            Self::UnalignedOffset => Cow::from("The offset is not aligned to the system allocation granularity"),
+            Self::AccessDenied => Cow::from(
+                "Access to the Memory Mapped File was denied. Either the handle lacks the rights for this, or a \
+                 privilege is missing, like SeCreateGlobalPrivilege for the Global namespace or SeLockMemoryPrivilege \
+                 for large pages",
+            ),
+            Self::NullPointer => Cow::from("A null pointer was passed where a valid one was required"),
+            Self::NotCommitted => Cow::from("The memory behind the Memory Mapped File is no longer committed"),
+            Self::NotReadLocked => Cow::from("Tried to release a read lock while the lock is held for writing"),
+            Self::NotWriteLocked => Cow::from("Tried to release a write lock that isn't held"),
+            Self::NoReadersToRelease => Cow::from("Tried to release a read lock while nobody is reading"),
+            Self::NameTooLong => Cow::from("The name of the MMF is longer than 63 bytes, including the namespace"),
             Self::GeneralFailure => Cow::from("No idea what the hell happened here..."),
             Self::OS_Err(c) => Cow::from(format!("E{c:02}: Generic OS Error")),
         };
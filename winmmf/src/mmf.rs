@@ -18,6 +18,8 @@
 //! keeping this maintanable and usable. If you need a more minimal implementation, feel free to yank whatever you need
 //! from here and instead building the crate without default features.
 
+#[cfg(feature = "impl_lock")]
+use super::states::{DynLock, SeqLock};
 use super::{
     err::{Error as MMFError, MMFResult},
     states::MMFLock,
@@ -32,20 +34,50 @@ use windows::{
     },
 };
 
+#[cfg(feature = "impl_mmf")]
+use microseh::ExceptionCode;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
 use std::cell::Cell;
+#[cfg(feature = "raw_access")]
+use std::ops::Deref;
 #[cfg(feature = "impl_mmf")]
-use std::{fmt, num::NonZeroUsize};
+use std::{
+    fmt,
+    hint::spin_loop,
+    mem::{align_of, size_of, ManuallyDrop},
+    num::{NonZeroU64, NonZeroUsize},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, AtomicU8, Ordering},
+        OnceLock,
+    },
+    thread::{sleep, yield_now},
+    time::{Duration, Instant},
+};
 #[cfg(feature = "impl_mmf")]
 use windows::{
     core::PCSTR,
     Win32::{
-        Foundation::{CloseHandle, GetLastError, INVALID_HANDLE_VALUE},
-        System::Memory::{CreateFileMappingA, MapViewOfFile, OpenFileMappingA, FILE_MAP_ALL_ACCESS, PAGE_READWRITE},
+        Foundation::{CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, ERROR_PRIVILEGE_NOT_HELD, INVALID_HANDLE_VALUE},
+        System::Memory::{
+            CreateFileMappingA, GetLargePageMinimum, MapViewOfFile, OpenFileMappingA, VirtualAlloc, VirtualLock,
+            VirtualQuery, VirtualUnlock, FILE_MAP, FILE_MAP_ALL_ACCESS, FILE_MAP_COPY, FILE_MAP_LARGE_PAGES,
+            FILE_MAP_READ, FILE_MAP_WRITE, MEMORY_BASIC_INFORMATION, MEM_COMMIT, PAGE_EXECUTE_READWRITE,
+            PAGE_EXECUTE_WRITECOPY, PAGE_PROTECTION_FLAGS, PAGE_READWRITE, PAGE_WRITECOPY, SEC_COMMIT, SEC_LARGE_PAGES,
+            SEC_RESERVE,
+        },
+        System::SystemInformation::{GetSystemInfo, SYSTEM_INFO},
     },
 };
 #[cfg(feature = "impl_mmf")]
 use windows_ext::ext::QWordExt;
 
+/// The amount of bytes the lock takes up at the start of the raw mapping, before the data.
+///
+/// Anything computing offsets into the raw mapping rather than the data should add this.
+pub const LOCK_SIZE: usize = 4;
+
 /// Local namespace prefix
 /// Use this to ensure only you and your child processes can read this.
 pub const LOCAL_NAMESPACE: ztr64 = ztr64::const_make("Local\\");
@@ -67,6 +99,20 @@ pub enum Namespace {
     CUSTOM = 2,
 }
 
+#[cfg(feature = "namespaces")]
+impl Namespace {
+    /// Get the prefix this namespace puts in front of MMF names, which is empty for [`CUSTOM`][Self::CUSTOM].
+    ///
+    /// These are the same strings as [`LOCAL_NAMESPACE`] and [`GLOBAL_NAMESPACE`], usable in const contexts.
+    pub const fn prefix(&self) -> &'static str {
+        match self {
+            Self::LOCAL => "Local\\",
+            Self::GLOBAL => "Global\\",
+            Self::CUSTOM => "",
+        }
+    }
+}
+
 /// We do a little transmutation, I'm an aclhemist!
 impl TryFrom<u8> for Namespace {
     /// Unit type, as we only need it for checking and never for more info.
@@ -80,6 +126,33 @@ impl TryFrom<u8> for Namespace {
     }
 }
 
+/// Parse namespaces from config files and CLI args, ignoring case.
+#[cfg(feature = "namespaces")]
+impl FromStr for Namespace {
+    /// Unit type, same as for [`TryFrom<u8>`]. It's either a namespace or it isn't.
+    type Err = ();
+    /// Accepts `local`, `global` and `custom` in any casing.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            _ if s.eq_ignore_ascii_case("local") => Ok(Self::LOCAL),
+            _ if s.eq_ignore_ascii_case("global") => Ok(Self::GLOBAL),
+            _ if s.eq_ignore_ascii_case("custom") => Ok(Self::CUSTOM),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Same as [`FromStr`], for the people who prefer `try_into()`
+#[cfg(feature = "namespaces")]
+impl TryFrom<&str> for Namespace {
+    /// Unit type, see [`FromStr`].
+    type Error = ();
+    /// Thin wrapper around [`str::parse`].
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 /// Mostly for debug purposes
 #[cfg(feature = "namespaces")]
 impl fmt::Display for Namespace {
@@ -102,22 +175,37 @@ impl fmt::Display for Namespace {
 pub trait Mmf {
     /// Read data from the MMF, return an owned Vec if all goes well.
     /// The standard implementation creates a new Vec and calls [`Self::read_to_buf`]
+    ///
+    /// The returned Vec holds exactly `min(count, size)` bytes, or all of the data if `count` is 0. Never more, and
+    /// never with a padded tail.
+    #[must_use = "ignoring read errors means using data that may not be there"]
     fn read(&self, count: usize) -> MMFResult<Vec<u8>>;
     /// Read data from the MMF into a provided buffer.
+    ///
+    /// The same length rules apply as for [`read`][Self::read]. Anything already in the buffer is overwritten, and its
+    /// length is set to the amount of bytes read.
+    #[must_use = "ignoring read errors means using data that may not be there"]
     fn read_to_buf(&self, buffer: &mut Vec<u8>, count: usize) -> MMFResult<()>;
     /// Read data into a raw pointer and pray it's valid
     ///
+    /// Returns the amount of bytes copied, which is less than `count` if the MMF is smaller than that. Anything in the
+    /// buffer past that amount is left as it was.
+    ///
     /// # Safety
     /// The caller is responsible to ensure the slice is big enough to read into.
-    unsafe fn read_to_raw(&self, buffer: *mut u8, count: usize) -> MMFResult<()>;
+    #[must_use = "ignoring read errors means using data that may not be there"]
+    unsafe fn read_to_raw(&self, buffer: *mut u8, count: usize) -> MMFResult<usize>;
     /// Allows for viewing the size without exposing the property.
+    #[must_use]
     fn size(&self) -> usize;
     /// Write data to the MMF.
+    #[must_use = "ignoring write errors means the data may never have been written"]
     fn write(&self, buffer: &[u8]) -> MMFResult<()>;
     /// Spin for `tries` times max, or until reading is allowed.
     ///
     /// This method takes an optional spinning function that returns a result. The spinning function must acquire the
     /// lock, and this function must unlock.
+    #[must_use = "ignoring read errors means using data that may not be there"]
     fn read_spin<F>(&self, count: usize, spinner: Option<F>) -> MMFResult<Vec<u8>>
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>;
@@ -125,17 +213,21 @@ pub trait Mmf {
     ///
     /// This method takes an optional spinning function that returns a result. The spinning function must acquire the
     /// lock, and this function must unlock.
+    #[must_use = "ignoring read errors means using data that may not be there"]
     fn read_to_buf_spin<F>(&self, buffer: &mut Vec<u8>, count: usize, spinner: Option<F>) -> MMFResult<()>
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>;
     /// Spin for `tries` times max, or until reading is allowed.
     ///
+    /// Returns the amount of bytes copied, like [`read_to_raw`][Mmf::read_to_raw] does.
+    ///
     /// # Safety
     /// See [`read_to_raw`][Mmf::read_to_raw]
     ///
     /// This method takes an optional spinning function that returns a result. The spinning function must acquire the
     /// lock, and this function must unlock.
-    unsafe fn read_to_raw_spin<F>(&self, buffer: *mut u8, count: usize, spinner: Option<F>) -> MMFResult<()>
+    #[must_use = "ignoring read errors means using data that may not be there"]
+    unsafe fn read_to_raw_spin<F>(&self, buffer: *mut u8, count: usize, spinner: Option<F>) -> MMFResult<usize>
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>;
     /// Spin for `tries` times max, or until writing is allowed.
@@ -143,6 +235,7 @@ pub trait Mmf {
     /// This method takes an optional spinning function that returns a result. The spinning function must acquire the
     /// lock, and this function must unlock.
     /// Defaults to [the one in `RWLock`][crate::states::RWLock]
+    #[must_use = "ignoring write errors means the data may never have been written"]
     fn write_spin<F>(&self, buffer: &[u8], spinner: Option<F>) -> MMFResult<()>
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>;
@@ -158,17 +251,24 @@ pub trait Mmf {
 /// map view.
 ///
 /// Supports both x86 and AMD64 by leveraging usize, to allow target-sized ints to be used everywhere.
-#[derive(Debug)]
+///
+/// ## Renaming
+/// There is no way to rename an MMF, or to make the same one reachable under a second name. The name belongs to the
+/// mapping object and is fixed when the OS creates it. The MMFs made here are backed by the pagefile, so there's no
+/// file either that a second mapping object could be created from. Every call with a new name creates a fresh mapping
+/// with its own memory. To move peers over to a new name, create an MMF under that name, [copy][Self::copy_from] the
+/// data over and have everyone switch. Aliasing only becomes possible for MMFs backed by an actual file on disk, which
+/// this crate doesn't support yet.
 pub struct MemoryMappedFile<LOCK: MMFLock> {
-    /// The [`HANDLE`] to the created mapping
-    handle: HANDLE,
+    /// The [`HANDLE`] to the created mapping, which may be swapped out when reopening it by name.
+    handle: Cell<HANDLE>,
+    /// The access the handle was opened with, which reopening it by name asks for again.
+    access: FILE_MAP,
     /// the "filename" portion
     name: ztr64,
     /// The higher order bits for the size of the opened file.
-    #[allow(dead_code)]
     size_high_order: u32,
     /// The lower order bits for the size of the opened file.
-    #[allow(dead_code)]
     size_low_order: u32,
     /// The total size, which is the bits of high and low order appeneded.
     size: usize,
@@ -176,8 +276,12 @@ pub struct MemoryMappedFile<LOCK: MMFLock> {
     lock: LOCK,
     /// The original MemoryMappedView; We need to keep this around for unmapping it.
     map_view: Option<MemoryMappedView>,
+    /// A separate view holding just the lock, if the main view doesn't start with it.
+    lock_view: Option<MemoryMappedView>,
     /// The pointer we can actually write into without fucking up the lock
     write_ptr: *mut u8,
+    /// Where the data starts in the raw mapping, [`LOCK_SIZE`] unless created with padding to align it.
+    data_offset: usize,
     /// A one-way changing cell to prevent using the MMF after closing it.
     closed: Cell<bool>,
     /// A bool to prevent writing through an MMF opened for reading
@@ -198,81 +302,311 @@ impl<LOCK: MMFLock> MemoryMappedFile<LOCK> {
     /// Violating these constraints _should_ result in a local namespace, but no guarantees are given and if it leads to
     /// UB, the heat death of the universe, panics or errors or a change in the answer to a value other than 42. you're
     /// on your own.
+    /// The name, namespace included, must fit in 63 bytes. Longer names error with
+    /// [`NameTooLong`][MMFError::NameTooLong], the same goes for opening MMFs. Every constructor takes the name as a
+    /// `&str`, so a `String` or [`ztr64`] is passed by reference.
     ///
     /// The size MUST be a non-zero value; allocating zero bytes errors on the OS end of things. Allocating too much
     /// will make a part of the file inaccessible to other code trying to read it from a 32-bit process.
     /// The total size allocated will be 4 bytes larger than the specified size, but only after checking the input size
     /// is non-zero.
-    pub fn new(size: NonZeroUsize, name: impl Into<ztr64>, namespace: Namespace) -> MMFResult<Self> {
+    pub fn new(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        // safety: `create` hands over a pointer to freshly zeroed lock bytes.
+        Self::create(size, None, name, namespace, LOCK_SIZE, PAGE_READWRITE, false, |ptr| unsafe {
+            LOCK::from_raw(ptr)
+        })
+    }
+
+    /// Create a new MMF with the data filled with `fill` rather than zeroes.
+    ///
+    /// A sentinel like `0xFF` makes it possible to tell "never written" apart from "written all zeroes". Otherwise this
+    /// works exactly like [`new`][Self::new].
+    pub fn new_filled(size: NonZeroUsize, name: &str, namespace: Namespace, fill: u8) -> MMFResult<Self> {
+        let mmf = Self::new(size, name, namespace)?;
+        mmf.fill(fill)?;
+        Ok(mmf)
+    }
+
+    /// Create a new Memory Mapped File with a 64-bit size, regardless of the pointer size of this process.
+    ///
+    /// The OS takes the size of the mapping as two 32-bit halves either way, so nothing stops a 32-bit process from
+    /// creating an MMF larger than 4GB for a 64-bit process to use. A 32-bit process can't map a view that large
+    /// though, so only the first `view_len` bytes of the data are mapped here, and [`size`][Mmf::size] reports the
+    /// size of that view. `view_len` is clamped to `size`. Pick a view that fits in the address space of the process,
+    /// and use [`open_range`][Self::open_range] to get at the rest of the data. When sharing across bitness, keep in
+    /// mind the 32-bit side can only ever see part of the data at once.
+    /// Otherwise this works exactly like [`new`][Self::new].
+    pub fn new_u64(size: NonZeroU64, view_len: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        let view_len = clamp_view(size, view_len);
+        // safety: `create` hands over a pointer to freshly zeroed lock bytes.
+        Self::create(view_len, Some(size), name, namespace, LOCK_SIZE, PAGE_READWRITE, false, |ptr| unsafe {
+            LOCK::from_raw(ptr)
+        })
+    }
+
+    /// Create a new MMF backed by large pages, to take the pressure off the TLB for MMFs of several gigabytes.
+    ///
+    /// The size is rounded up so the MMF including the lock is a multiple of the large page size, which is 2MiB on
+    /// most systems. Large pages are always committed up front and can't be paged out.
+    ///
+    /// This requires the `SeLockMemoryPrivilege`, which needs to be both granted to the user and enabled for the
+    /// process. Without it, this errors with [`AccessDenied`][MMFError::AccessDenied] instead of quietly falling back
+    /// to normal pages. Systems without large page support error with [`GeneralFailure`][MMFError::GeneralFailure].
+    pub fn new_large_pages(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        // Safety: this just asks the OS for a number.
+        let large_page = unsafe { GetLargePageMinimum() };
+        if large_page == 0 {
+            return Err(MMFError::GeneralFailure);
+        }
+        let total = size.get().checked_add(LOCK_SIZE).ok_or(MMFError::NotEnoughMemory)?;
+        let rounded = total.checked_next_multiple_of(large_page).ok_or(MMFError::NotEnoughMemory)?;
+        // Can't be zero, it's at least `LOCK_SIZE` smaller than a non-zero multiple of the large page size.
+        let size = NonZeroUsize::new(rounded - LOCK_SIZE).ok_or(MMFError::GeneralFailure)?;
+        Self::create(
+            size,
+            None,
+            name,
+            namespace,
+            LOCK_SIZE,
+            PAGE_READWRITE | SEC_COMMIT | SEC_LARGE_PAGES,
+            false,
+            // safety: `create` hands over a pointer to freshly zeroed lock bytes.
+            |ptr| unsafe { LOCK::from_raw(ptr) },
+        )
+        .map_err(|e| match e {
+            MMFError::OS_Err(err) if err.code() == ERROR_PRIVILEGE_NOT_HELD.to_hresult() => MMFError::AccessDenied,
+            e => e,
+        })
+    }
+
+    /// Create a new MMF that only reserves its pages, leaving them to be committed as they're needed.
+    ///
+    /// This lets a sparse MMF claim a huge size, while only the pages actually used take up memory. Only the page
+    /// holding the lock is committed up front; use [`commit_range`][Self::commit_range] to commit the parts of the data
+    /// you're going to use, in every process, before using them. Committed pages start out zeroed and can't be
+    /// decommitted again. Touching a page that isn't committed raises an access violation. Reads and writes through
+    /// [`Mmf`] catch it and error with [`MMF_NotFound`][MMFError::MMF_NotFound], anything else needs to stay within
+    /// committed ranges. Otherwise this works exactly like [`new`][Self::new].
+    pub fn new_reserved(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        // safety: `create` hands over a pointer to freshly committed, and thus zeroed, lock bytes.
+        Self::create(size, None, name, namespace, LOCK_SIZE, PAGE_READWRITE | SEC_RESERVE, false, |ptr| unsafe {
+            LOCK::from_raw(ptr)
+        })
+    }
+
+    /// Create a new MMF with the data aligned to `align` bytes, for storing types that need more than 4 byte alignment.
+    ///
+    /// The data normally starts right after the [`LOCK_SIZE`] lock bytes, which only guarantees 4 byte alignment. Here
+    /// the lock is followed by enough padding to put the data at a multiple of `align` from the page aligned start of
+    /// the mapping, see [`data_offset`][Self::data_offset]. `align` must be a power of two no larger than
+    /// [the page size][system_page_size], or this errors with [`UnalignedOffset`][MMFError::UnalignedOffset].
+    /// Other processes don't know about the padding, they need to open the MMF with
+    /// [`open_aligned`][Self::open_aligned] using the same `align`. Otherwise this works exactly like
+    /// [`new`][Self::new].
+    pub fn new_aligned(size: NonZeroUsize, name: &str, namespace: Namespace, align: usize) -> MMFResult<Self> {
+        let data_offset = aligned_data_offset(align)?;
+        // safety: `create` hands over a pointer to freshly zeroed lock bytes.
+        Self::create(size, None, name, namespace, data_offset, PAGE_READWRITE, false, |ptr| unsafe {
+            LOCK::from_raw(ptr)
+        })
+    }
+
+    /// Create a new MMF with room for exactly one `T`, with the data aligned for it.
+    ///
+    /// The size is `size_of::<T>()`, which includes any padding `T` needs, and the lock bytes are added on top as
+    /// always. Types aligned to more than [`LOCK_SIZE`] bytes get padding after the lock like
+    /// [`new_aligned`][Self::new_aligned] adds, so other processes open these with
+    /// [`open_aligned`][Self::open_aligned] passing `align_of::<T>()`. Zero sized types error with
+    /// [`NotEnoughMemory`][MMFError::NotEnoughMemory]. Otherwise this works exactly like [`new`][Self::new].
+    pub fn new_for<T>(name: &str, namespace: Namespace) -> MMFResult<Self> {
+        let size = NonZeroUsize::new(size_of::<T>()).ok_or(MMFError::NotEnoughMemory)?;
+        Self::new_aligned(size, name, namespace, align_of::<T>())
+    }
+
+    /// Create a new MMF with the given page protection, shared by all constructors.
+    ///
+    /// `make_lock` builds the lock from a pointer to the zeroed lock bytes, it's initialized afterwards. The data
+    /// starts `data_offset` bytes into the mapping, which is at least [`LOCK_SIZE`]. If `attach` is true and the OS
+    /// reports the name is taken, the existing MMF is attached to as is, without zeroing it or touching its lock.
+    /// Only `view_size` bytes of data are mapped. The data of the mapping is `size` bytes, or as large as the view if
+    /// that's `None`. The handle is closed on errors.
+    #[allow(clippy::too_many_arguments)]
+    fn create(
+        view_size: NonZeroUsize,
+        size: Option<NonZeroU64>,
+        name: &str,
+        namespace: Namespace,
+        data_offset: usize,
+        protection: PAGE_PROTECTION_FLAGS,
+        attach: bool,
+        make_lock: impl FnOnce(*mut u8) -> LOCK,
+    ) -> MMFResult<Self> {
         // Build the name to use for the MMF
-        let init_name = match namespace {
-            Namespace::GLOBAL => GLOBAL_NAMESPACE,
-            Namespace::LOCAL => LOCAL_NAMESPACE,
-            Namespace::CUSTOM => ztr64::new(),
-        } + name.into();
+        let init_name = full_name(name, namespace)?;
 
         // fuckin' windows
         let mmf_name = PCSTR::from_raw(init_name.to_ptr());
-        let (dw_low, dw_high) = (size.get() + 4).split();
+        let size = size.map_or_else(|| size_u64(view_size), Ok)?;
+        let (dw_low, dw_high) = size.get().checked_add(data_offset as u64).ok_or(MMFError::NotEnoughMemory)?.split();
+        let view_size = view_size.get();
+        let raw_view_size = view_size.checked_add(data_offset).ok_or(MMFError::NotEnoughMemory)?;
+        let access = if protection.contains(SEC_LARGE_PAGES) {
+            FILE_MAP_ALL_ACCESS | FILE_MAP_LARGE_PAGES
+        } else {
+            FILE_MAP_ALL_ACCESS
+        };
 
         // Safety: handled through microSEH and we check the last error status later. Failure here is failure there.
         let handle = try_seh(|| unsafe {
-            CreateFileMappingA(INVALID_HANDLE_VALUE, None, PAGE_READWRITE, dw_high, dw_low, mmf_name)
+            CreateFileMappingA(INVALID_HANDLE_VALUE, None, protection, dw_high, dw_low, mmf_name)
         })??;
+        // Checked right away, as anything called after this may overwrite the last error.
+        if attach && unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+            return Self::attach(handle, init_name, view_size, data_offset, access);
+        }
 
-        // Unsafe because `MapViewOfFile` is marked as such, but it should return a NULL pointer when failing; and set
-        // the last error state correspondingly.
-        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, size.get() + 4) })?;
+        let map_view = match MemoryMappedView::map_with_access(handle, 0, raw_view_size, access) {
+            Ok(map_view) => map_view,
+            Err(e) => {
+                // Nothing else owns the handle yet, so clean it up before bailing.
+                _ = unsafe { CloseHandle(handle) };
+                return Err(e);
+            }
+        };
 
-        // Explicit check to make sure we have something that works (later is now)
-        if unsafe { GetLastError() }.is_err() {
-            return Err(WErr::from_win32().into());
+        if protection.contains(SEC_RESERVE) {
+            // Writing zeroes would touch pages that don't exist yet. The OS zeroes them as they get committed anyway.
+            if let Err(e) = commit_pages(map_view.as_ptr().cast(), LOCK_SIZE) {
+                // Unmap the view before closing the handle, the same order dropping an MMF goes in.
+                drop(map_view);
+                _ = unsafe { CloseHandle(handle) };
+                return Err(e);
+            }
+        } else {
+            // Waste some time to ensure the memory is zeroed out - I learned the importance of this the hard way.
+            let zeroing = vec![0; raw_view_size];
+            // safety: we're writing zeroes into memory we just got back from the OS
+            unsafe { std::ptr::copy(zeroing.as_ptr(), map_view.as_ptr(), zeroing.len()) };
         }
 
-        // Waste some time to ensure the memory is zeroed out - I learned the importance of this the hard way.
-        let zeroing = vec![0; size.get() + 4];
-        // safety: we're writing zeroes into memory we just got back from the OS
-        unsafe { std::ptr::copy(zeroing.as_ptr(), map_view.Value.cast(), zeroing.len()) };
-
-        // safety: we just zeroed this memory out and we're initializing it freshly
-        let lock = unsafe { LOCK::from_raw(map_view.Value.cast()).initialize() };
-        let write_ptr = unsafe { map_view.Value.cast::<u8>().add(4) };
+        // we just zeroed this memory out and we're initializing it freshly
+        let lock = make_lock(map_view.as_ptr()).initialize();
+        let write_ptr = unsafe { map_view.as_ptr().add(data_offset) };
         Ok(Self {
-            handle,
+            handle: Cell::new(handle),
+            access,
             name: init_name,
             size_high_order: dw_high,
             size_low_order: dw_low,
-            size: size.get(),
-            map_view: Some(map_view.into()),
+            size: view_size,
+            map_view: Some(map_view),
+            lock_view: None,
             lock,
             write_ptr,
+            data_offset,
             closed: Cell::new(false),
             readonly: false,
         })
     }
 
+    /// Attach to the existing MMF behind `handle`, which [`create`][Self::create] got back for a name that was taken.
+    ///
+    /// The entire mapping is viewed, so an MMF smaller than `view_size` plus `data_offset` errors with
+    /// [`NotEnoughMemory`][MMFError::NotEnoughMemory] rather than failing to map. The handle is closed on errors.
+    fn attach(handle: HANDLE, name: ztr64, view_size: usize, data_offset: usize, access: FILE_MAP) -> MMFResult<Self> {
+        let map_view = match MemoryMappedView::map_with_access(handle, 0, 0, access) {
+            Ok(map_view) => map_view,
+            Err(e) => {
+                // Nothing else owns the handle yet, so clean it up before bailing.
+                _ = unsafe { CloseHandle(handle) };
+                return Err(e);
+            }
+        };
+        // Safety: the lock bytes are at the start of the mapping, and whoever created it initialized them.
+        let lock = unsafe { LOCK::from_existing(map_view.as_ptr()) };
+        let write_ptr = unsafe { map_view.as_ptr().add(data_offset) };
+        let (dw_low, dw_high) = (view_size + data_offset).split();
+        let mmf = Self {
+            handle: Cell::new(handle),
+            access,
+            name,
+            size_high_order: dw_high,
+            size_low_order: dw_low,
+            size: view_size,
+            lock,
+            map_view: Some(map_view),
+            lock_view: None,
+            write_ptr,
+            data_offset,
+            closed: Cell::new(false),
+            readonly: false,
+        };
+
+        // Same as in `open`, dropping `mmf` takes care of unmapping and closing the handle.
+        if mmf.map_view.as_ref().map(MemoryMappedView::region_size).transpose()?.unwrap_or_default()
+            < view_size + data_offset
+        {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        Ok(mmf)
+    }
+
     /// Open an existing MMF, if it exists.
     ///
     /// Defaults to read and write permissions, use the exposed wrappers to open R or RW
     /// I have no idea what happens if you call this on a fake name. Code responsibly.
     /// In all reality though, it should return an error that you can handle.
     pub fn open(size: NonZeroUsize, name: &str, namespace: Namespace, readonly: bool) -> MMFResult<Self> {
+        Self::open_with_access(size, name, namespace, readonly, FILE_MAP_ALL_ACCESS, LOCK_SIZE)
+    }
+
+    /// Open an existing MMF created by [`new_aligned`][Self::new_aligned], which must be passed the same `align`.
+    ///
+    /// There's no way to tell from the mapping how much padding its creator put after the lock, so getting `align`
+    /// wrong has this read and write the wrong bytes. Otherwise this works exactly like [`open`][Self::open].
+    pub fn open_aligned(
+        size: NonZeroUsize,
+        name: &str,
+        namespace: Namespace,
+        align: usize,
+        readonly: bool,
+    ) -> MMFResult<Self> {
+        Self::open_with_access(size, name, namespace, readonly, FILE_MAP_ALL_ACCESS, aligned_data_offset(align)?)
+    }
+
+    /// Open an existing MMF with a private, copy-on-write view.
+    ///
+    /// Writes through this MMF are local-only; the OS copies each page on the first write to it and from then on this
+    /// process sees its own copy of that page. Peers never see these writes, and changes they make to pages this
+    /// process already wrote to are no longer visible here. This also goes for the lock, which lives in the first
+    /// page: once this process locks the MMF, it stops participating in the shared lock state. Use this for sandboxing
+    /// things like parsers against shared input, not for communicating.
+    pub fn open_cow(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        Self::open_with_access(size, name, namespace, false, FILE_MAP_COPY, LOCK_SIZE)
+    }
+
+    /// Open an existing MMF, requesting `access` for both the handle and the view. The data starts `data_offset` bytes
+    /// into the mapping.
+    fn open_with_access(
+        size: NonZeroUsize,
+        name: &str,
+        namespace: Namespace,
+        readonly: bool,
+        access: FILE_MAP,
+        data_offset: usize,
+    ) -> MMFResult<Self> {
         // Build the name to use for the MMF
-        let init_name = match namespace {
-            Namespace::GLOBAL => ztr64::make(&format!("{GLOBAL_NAMESPACE}{name}")),
-            Namespace::LOCAL => ztr64::make(&format!("{LOCAL_NAMESPACE}{name}")),
-            Namespace::CUSTOM => ztr64::make(name),
-        };
+        let init_name = full_name(name, namespace)?;
         // fuckin' windows
         let mmf_name = PCSTR::from_raw(init_name.to_ptr());
-        let (dw_low, dw_high) = (size.get() + 4).split();
+        let (dw_low, dw_high) = (size.get() + data_offset).split();
 
         // Safety: Issues here are issues later, and we check for them later.
-        let handle = try_seh(|| unsafe { OpenFileMappingA(FILE_MAP_ALL_ACCESS.0, false, mmf_name) })??;
+        let handle = try_seh(|| unsafe { OpenFileMappingA(access.0, false, mmf_name) })??;
 
         // Unsafe because `MapViewOfFile` is marked as such, but it should return a NULL pointer when failing; and set
         // the last error state correspondingly.
-        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, size.get() + 4) })?;
+        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, access, 0, 0, size.get() + data_offset) })?;
 
         // Explicit check to make sure we have something that works (later is now)
         if unsafe { GetLastError() }.is_err() {
@@ -281,33 +615,235 @@ impl<LOCK: MMFLock> MemoryMappedFile<LOCK> {
 
         // Safety: We know where these bytes come from (ideally, they were opened by this lib)
         let lock = unsafe { LOCK::from_existing(map_view.Value.cast()) };
-        let write_ptr = unsafe { map_view.Value.cast::<u8>().add(4) };
-        Ok(Self {
-            handle,
+        let write_ptr = unsafe { map_view.Value.cast::<u8>().add(data_offset) };
+        let mmf = Self {
+            handle: Cell::new(handle),
+            access,
             name: init_name,
             size_high_order: dw_high,
             size_low_order: dw_low,
             size: size.get(),
             lock,
             map_view: Some(map_view.into()),
+            lock_view: None,
             write_ptr,
+            data_offset,
             closed: Cell::new(false),
             readonly,
-        })
+        };
+
+        // Asking for more than the creator allocated would have reads and writes run off the end of the view. Bail
+        // before that can happen, dropping `mmf` takes care of unmapping and closing the handle.
+        let committed = mmf.map_view.as_ref().map(MemoryMappedView::region_size).transpose()?.unwrap_or_default();
+        if committed < size.get() + data_offset {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        Ok(mmf)
     }
 
-    /// Open an MMF for reading
+    /// Open an MMF for reading, with the data mapped read-only.
     ///
-    /// Wrapper around [`open`][Self::open] that always passes true
+    /// Wrapper around [`open_range`][Self::open_range] for the entire data, passing true for `readonly`. Only the lock
+    /// bytes are mapped writable, so a stray write through a raw pointer into the data faults rather than corrupting
+    /// it. This is not a least-privilege open: every reader takes the lock, and taking it writes to the lock bytes, so
+    /// the handle is opened with read and write access. Processes that may only read the MMF can't open it and error
+    /// with [`AccessDenied`][MMFError::AccessDenied].
     pub fn open_read(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
-        Self::open(size, name, namespace, true)
+        Self::open_range(name, namespace, 0, size, true)
     }
 
     /// Open an MMF for reading and writing
     ///
-    /// Wrapper around [`open`][Self::open] that always passes false
+    /// Wrapper around [`open`][Self::open] that always passes false, and makes sure writing will actually work. The OS
+    /// already refuses to map a writable view of an MMF that was created read-only, and on top of that the protection
+    /// of the pages behind the view is checked. Either way, an MMF that can't be written errors with
+    /// [`AccessDenied`][MMFError::AccessDenied] right here, rather than faulting on the first write.
     pub fn open_write(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
-        Self::open(size, name, namespace, false)
+        let mmf = Self::open(size, name, namespace, false)?;
+        if !mmf.map_view.as_ref().ok_or(MMFError::MMF_NotFound)?.writable()? {
+            return Err(MMFError::AccessDenied);
+        }
+        Ok(mmf)
+    }
+
+    /// Open only part of an existing MMF, for arenas too large to map in full in every process.
+    ///
+    /// `offset` and `len` describe a range in the data, the lock bytes at the start of the MMF are accounted for. The
+    /// OS only maps views starting at a multiple of [the allocation granularity][system_allocation_granularity], so the
+    /// view starts at the closest aligned offset before the range. Only `len` bytes are accessible through the result
+    /// either way. The lock gets a tiny view of its own, so every process keeps sharing the same lock.
+    ///
+    /// If `readonly` is true, the data is mapped read-only like [`freeze`][Self::freeze] does, and the MMF is opened
+    /// with read and write access only rather than full access.
+    pub fn open_range(
+        name: &str,
+        namespace: Namespace,
+        offset: u64,
+        len: NonZeroUsize,
+        readonly: bool,
+    ) -> MMFResult<Self> {
+        // Build the name to use for the MMF
+        let init_name = full_name(name, namespace)?;
+        let mmf_name = PCSTR::from_raw(init_name.to_ptr());
+        let raw_offset = offset.checked_add(LOCK_SIZE as u64).ok_or(MMFError::NotEnoughMemory)?;
+        let aligned = align_offset(raw_offset);
+        // Less than the allocation granularity, which is a u32.
+        let skip = (raw_offset - aligned) as usize;
+        let view_len = skip.checked_add(len.get()).ok_or(MMFError::NotEnoughMemory)?;
+        let (dw_low, dw_high) = (raw_offset + len.get() as u64).split();
+
+        // The lock always needs to be writable, but readers don't need any more than that.
+        let (access, data_access) = if readonly {
+            (FILE_MAP_READ | FILE_MAP_WRITE, FILE_MAP_READ)
+        } else {
+            (FILE_MAP_ALL_ACCESS, FILE_MAP_ALL_ACCESS)
+        };
+
+        // Safety: Issues here are issues later, and we check for them later.
+        let handle = try_seh(|| unsafe { OpenFileMappingA(access.0, false, mmf_name) })??;
+        let views = MemoryMappedView::map(handle, 0, LOCK_SIZE).and_then(|lock_view| {
+            Ok((lock_view, MemoryMappedView::map_with_access(handle, aligned, view_len, data_access)?))
+        });
+        let (lock_view, map_view) = match views {
+            Ok(views) => views,
+            Err(e) => {
+                // Nothing else owns the handle yet, so clean it up before bailing.
+                _ = unsafe { CloseHandle(handle) };
+                return Err(e);
+            }
+        };
+
+        // Safety: We know where these bytes come from (ideally, they were opened by this lib)
+        let lock = unsafe { LOCK::from_existing(lock_view.as_ptr()) };
+        let write_ptr = unsafe { map_view.as_ptr().add(skip) };
+        let mmf = Self {
+            handle: Cell::new(handle),
+            access,
+            name: init_name,
+            size_high_order: dw_high,
+            size_low_order: dw_low,
+            size: len.get(),
+            lock,
+            map_view: Some(map_view),
+            lock_view: Some(lock_view),
+            write_ptr,
+            data_offset: LOCK_SIZE,
+            closed: Cell::new(false),
+            readonly,
+        };
+
+        // Same as in `open`, don't let reads and writes run off the end of the view.
+        if mmf.map_view.as_ref().map(MemoryMappedView::region_size).transpose()?.unwrap_or_default() < view_len {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        Ok(mmf)
+    }
+
+    /// Open an MMF of at least `size` bytes if it exists, or create it if it doesn't.
+    ///
+    /// This is a single call to the OS, which either creates the MMF or hands back the existing one. There is no window
+    /// between checking for the MMF and creating it in which another process could create it too, so any number of
+    /// processes can race to call this and they all end up sharing one MMF. Only the process that actually created it
+    /// zeroes the data and initializes the lock, everyone else attaches to it as is.
+    ///
+    /// This doesn't grow existing MMFs. Their size is fixed by the OS when they're created, and there is no way to
+    /// resize or remap one in place. If the existing MMF is smaller than `size`, this errors with
+    /// [`NotEnoughMemory`][MMFError::NotEnoughMemory]. The only way to get a bigger one is to close every handle to
+    /// it, so the OS destroys it, and create it anew. Keep in mind the OS rounds sizes up to whole pages, so an
+    /// existing MMF may be opened with a slightly larger size than it was created with.
+    ///
+    /// The same error is returned when the OS itself runs out of memory or system resources while creating or mapping
+    /// the MMF, so it doesn't tell an existing MMF being too small apart from a failed allocation.
+    pub fn open_or_create(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        // safety: `create` only hands over lock bytes when it created the MMF, in which case they're freshly zeroed.
+        Self::create(size, None, name, namespace, LOCK_SIZE, PAGE_READWRITE, true, |ptr| unsafe { LOCK::from_raw(ptr) })
+    }
+
+    /// Open an existing MMF with a 64-bit size.
+    ///
+    /// As with [`new_u64`][Self::new_u64], only the first `view_len` bytes of the data are mapped, clamped to `size`.
+    /// The OS can't tell how large a mapping is without viewing all of it, so only the view is checked to fit.
+    /// Otherwise this works exactly like [`open`][Self::open].
+    pub fn open_u64(
+        size: NonZeroU64,
+        view_len: NonZeroUsize,
+        name: &str,
+        namespace: Namespace,
+        readonly: bool,
+    ) -> MMFResult<Self> {
+        Self::open(clamp_view(size, view_len), name, namespace, readonly)
+    }
+
+    /// Check that the MMF is still usable, e.g. after a process sharing it crashed.
+    ///
+    /// Errors with [`MMF_NotFound`][MMFError::MMF_NotFound] if the MMF was closed or its view unmapped, with
+    /// [`NotCommitted`][MMFError::NotCommitted] if the memory behind the view is no longer committed, and with
+    /// [`Uninitialized`][MMFError::Uninitialized] if the lock doesn't read as initialized.
+    pub fn health_check(&self) -> MMFResult<()> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let views = [self.map_view.as_ref().ok_or(MMFError::MMF_NotFound)?]
+            .into_iter()
+            .chain(self.lock_view.as_ref());
+        for view in views {
+            if !view.committed()? {
+                return Err(MMFError::NotCommitted);
+            }
+        }
+        if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        Ok(())
+    }
+
+    /// Turn this into a readonly MMF, so every write from here on errors with [`MMF_NotFound`][MMFError::MMF_NotFound].
+    ///
+    /// This is a cheap way to hand the MMF to code that should only read, without reopening it. There is no way back.
+    /// The view itself stays mapped as it was, so this only guards the safe API.
+    pub fn into_readonly(mut self) -> Self {
+        self.readonly = true;
+        self
+    }
+
+    /// Remap the data read-only, so even writes through raw pointers into it can't change it anymore.
+    ///
+    /// Unlike [`into_readonly`][Self::into_readonly], this doesn't just guard the safe API: the writable view of the
+    /// data is swapped for one mapped with read access only. Writes through the safe API error the same way, but a
+    /// stray write through a pointer into the data now faults instead of corrupting it. The lock moves to a tiny view
+    /// of its own so locking keeps working, and is attached to anew with [`from_existing`][MMFLock::from_existing].
+    /// Locks this instance still holds are released first. The new views are mapped before the old one is unmapped,
+    /// so the MMF is left as it was if mapping fails. There is no way back, and any pointers into the data from before
+    /// freezing are invalid afterwards. Freezing a copy-on-write MMF loses its private changes, the new view shows the
+    /// data every other process sees.
+    pub fn freeze(&mut self) -> MMFResult<()> {
+        // The data doesn't always start right at the view, `open_range` may have had to align the view down.
+        let skip = self.view_skip()?;
+        let handle = self.handle.get();
+        let view_len = skip + self.size;
+        // Views with the lock in them start at the very beginning of the MMF, ranges are aligned back from their end.
+        let offset = match self.lock_view {
+            None => 0,
+            Some(_) => ((u64::from(self.size_high_order) << 32) | u64::from(self.size_low_order)) - view_len as u64,
+        };
+
+        let map_view = MemoryMappedView::map_with_access(handle, offset, view_len, FILE_MAP_READ)?;
+        // Should this fail, the read-only view is unmapped again on the way out and nothing has changed.
+        let lock_view = match self.lock_view.take() {
+            Some(lock_view) => lock_view,
+            None => MemoryMappedView::map(handle, 0, LOCK_SIZE)?,
+        };
+
+        self.release_held_locks();
+        // Safety: the lock bytes behind the new view are the very same ones the current lock uses.
+        let lock = unsafe { LOCK::from_existing(lock_view.as_ptr()) };
+        // The old lock and view go away here, in that order, so nothing points into unmapped memory.
+        drop(std::mem::replace(&mut self.lock, lock));
+        self.write_ptr = unsafe { map_view.as_ptr().add(skip) };
+        self.map_view = Some(map_view);
+        self.lock_view = Some(lock_view);
+        self.readonly = true;
+        Ok(())
     }
 
     /// Check if this MMF can be written to
@@ -320,115 +856,1459 @@ impl<LOCK: MMFLock> MemoryMappedFile<LOCK> {
         !self.closed.get() && self.lock.initialized()
     }
 
-    /// Get the namespace of the file, if any. If an empty string is returned, it's Local.
-    pub fn namespace(&self) -> String {
-        self.name.split_once('\\').unwrap_or_default().0.to_owned()
+    /// Check if [`close`][Self::close] has been called on this MMF, without checking the lock.
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
     }
 
-    /// Return the filename the MMF is bound to, which is only the whole name if no namespace is provided.
-    pub fn filename(&self) -> String {
-        self.name.split_once('\\').map(|s| s.1.to_owned()).unwrap_or(self.name.to_string())
+    /// Wait for the lock to be initialized, or give up once `timeout` has passed.
+    ///
+    /// Opening an MMF the instant another process created it may race that process' call to
+    /// [`initialize`][MMFLock::initialize]. This spins on [`MMFLock::initialized`] with a small backoff, first
+    /// spinning on the CPU and then yielding to the scheduler, until the lock reports being initialized. If the
+    /// timeout passes first, [`Uninitialized`][MMFError::Uninitialized] is returned. Closed MMFs error immediately.
+    pub fn wait_for_init(&self, timeout: Duration) -> MMFResult<()> {
+        let initialized = poll_until(Instant::now().checked_add(timeout), || {
+            if self.closed.get() {
+                Err(MMFError::MMF_NotFound)
+            } else {
+                Ok(self.lock.initialized())
+            }
+        })?;
+        if initialized {
+            Ok(())
+        } else {
+            Err(MMFError::Uninitialized)
+        }
     }
 
-    /// Returns the stored name, which should be `[Namespace\]<FileName>`
-    pub fn fullname(&self) -> String {
-        self.name.to_string()
+    /// Read into `buf`, reusing whatever it has allocated already.
+    ///
+    /// The buffer is cleared first and only grows if its capacity is short of the bytes to read, so a loop that keeps
+    /// passing in the same buffer stops allocating once it has read the largest `count` once. This is the method for
+    /// hot read loops, [`read`][Mmf::read] allocates a new Vec every call. Length rules and errors are the same as for
+    /// [`read_to_buf`][Mmf::read_to_buf]. On errors, `buf` is left empty.
+    pub fn read_into(&self, buf: &mut Vec<u8>, count: usize) -> MMFResult<()> {
+        buf.clear();
+        self.read_to_buf(buf, count)
     }
 
-    /// Close the MMF. Don't worry about calling this, it's handled in [`Drop`].
-    pub fn close(&self) -> MMFResult<()> {
-        self.closed.set(true);
-        // Safety: microSEH handles the OS side of this error, and the match handles this end.
-        match try_seh(|| unsafe { CloseHandle(self.handle) })?.map_err(MMFError::from) {
-            Err(MMFError::OS_OK(_)) | Ok(_) => Ok(()),
-            err => err.map_err(|e| {
-                eprintln!("Error closing MMF's handle: {:#?}", e);
-                e
-            }),
-        }
+    /// Copy the entire data into a new Vec, whose length and capacity are both exactly [`size`][Mmf::size].
+    ///
+    /// [`read`][Mmf::read] only promises the length, the allocator is free to hand out more capacity than asked for.
+    /// This goes through [`to_boxed_slice`][Self::to_boxed_slice], which drops any spare capacity, so the result is
+    /// safe to pass to code assuming `len() == capacity()`. Errors are the same as for [`read`][Mmf::read].
+    pub fn to_vec(&self) -> MMFResult<Vec<u8>> {
+        self.to_boxed_slice().map(Vec::from)
     }
-}
 
-/// Implements a usable file-like interface for working with an MMF. Pass all input as bytes, please.
-#[cfg(feature = "impl_mmf")]
-impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
-    /// Attempts to read bytes up to the entirety of the data as defined in [`Self::size`].
+    /// Copy the entire data into a new boxed slice of exactly [`size`][Mmf::size] bytes.
     ///
-    /// This function succeeds if there is a value in [`Self::map_view`] but it cannot guarantee the data returned is
-    /// correct. This is an unfortunate side effect of having to work with raw pointers and bytes in memory.
-    /// Assuming nothing external has touched the memory region other than this class, it _should_ be valid data unless
-    /// it's marked as uninitialized. The returned error for this is an instance of the
-    /// [crate's error enum][crate::err::Error]
+    /// Errors are the same as for [`read`][Mmf::read].
+    pub fn to_boxed_slice(&self) -> MMFResult<Box<[u8]>> {
+        self.read(0).map(Vec::into_boxed_slice)
+    }
+
+    /// Spinning form of [`read`][Mmf::read] that gives up after `max_tries`.
     ///
-    /// - 1: Write Protected; the file has a write lock on it which means reading might return incomplete data, or the
-    ///   maximum amount of readers has been reached (this should not happen assuming all implementations are clean).
-    /// - 2: Invalid block; the lock is telling us this data has not yet been initialized.
-    /// - 5: File not found; the MMF isn't opened yet or no map view exists.
-    #[inline]
-    fn read(&self, count: usize) -> Result<Vec<u8>, MMFError> {
-        let mut buf = Vec::with_capacity(self.size);
-        self.read_to_buf(&mut buf, count)?;
-        Ok(buf)
+    /// This uses the lock's own [`spin_and_lock_read`][MMFLock::spin_and_lock_read] with a finite cap rather than the
+    /// `usize::MAX` used by [`read_spin`][Mmf::read_spin] when no spinner is provided. Running out of tries errors with
+    /// [`MaxTriesReached`][MMFError::MaxTriesReached]. Use `read_spin` with a custom spinner for custom backoff.
+    pub fn read_spin_tries(&self, count: usize, max_tries: usize) -> MMFResult<Vec<u8>> {
+        self.read_spin(count, Some(|_: &dyn MMFLock, _| LOCK::spin_and_lock_read(&self.lock, max_tries)))
     }
 
-    /// Spinning form of [`read`][Self::read]
-    fn read_spin<F>(&self, count: usize, spinner: Option<F>) -> MMFResult<Vec<u8>>
-    where
-        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
-    {
-        let mut buf = Vec::with_capacity(self.size);
-        self.read_to_buf_spin(&mut buf, count, spinner)?;
-        Ok(buf)
+    /// Spinning form of [`write`][Mmf::write] that gives up after `max_tries`.
+    ///
+    /// See [`read_spin_tries`][Self::read_spin_tries], this is the same but for [`write_spin`][Mmf::write_spin].
+    pub fn write_spin_tries(&self, buffer: &[u8], max_tries: usize) -> MMFResult<()> {
+        self.write_spin(buffer, Some(|_: &dyn MMFLock, _| LOCK::spin_and_lock_write(&self.lock, max_tries)))
     }
 
-    /// See the documentation for [Self::read()], except this takes a buffer to write to.
+    /// Read fresh data into `fallback` if the read lock can be taken within `timeout`, or leave it as it is if not.
     ///
-    /// If the count is 0, the entire MMF will be read into the buffer. If the buffer is smaller than the amount of data
-    /// to be read, it _will be grown_ to fit the requested data, using [`Vec::reserve_exact`]. The returned error for
-    /// this is an instance of the [crate's error enum][crate::err::Error]
-    fn read_to_buf(&self, buffer: &mut Vec<u8>, count: usize) -> MMFResult<()> {
-        let buf_cap = buffer.capacity();
-        let to_read = if count == 0 { self.size } else { count };
+    /// Returns `true` if the data in `fallback` is stale, because the lock stayed contended until `timeout` passed.
+    /// This is for consumers that would rather keep going with the previous data than block, like a display that
+    /// redraws every frame. Fresh data follows the same length rules as [`read_to_buf`][Mmf::read_to_buf]. Any other
+    /// errors are returned as usual, leaving `fallback` untouched.
+    pub fn read_or_stale(&self, count: usize, timeout: Duration, fallback: &mut Vec<u8>) -> MMFResult<bool> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        match lock_until(Instant::now().checked_add(timeout), || self.lock.lock_read()) {
+            Err(MMFError::MaxTriesReached) => return Ok(true),
+            locked => locked?,
+        }
 
-        if buf_cap < to_read {
-            buffer.reserve_exact(to_read - buf_cap);
+        let to_read = if count == 0 { self.size } else { count.min(self.size) };
+        fallback.clear();
+        fallback.reserve_exact(to_read);
+        // safety: the buffer was emptied and grown to fit `to_read` bytes, which is clamped to the size of the MMF.
+        let copied = unsafe { guarded_copy(self.write_ptr, fallback.as_mut_ptr(), to_read) };
+        if copied.is_ok() {
+            // safety: all `to_read` bytes were copied in.
+            unsafe { fallback.set_len(to_read) };
         }
-        unsafe {
-            self.read_to_raw(buffer.as_mut_ptr(), count)?;
-            buffer.set_len(to_read);
+        let unlocked = self.lock.unlock_read();
+        copied.and(unlocked).map(|()| false)
+    }
+
+    /// Copy the data from another MMF into this one, without an intermediate buffer.
+    ///
+    /// This takes a read lock on `src` and a write lock on `self`, then copies as many bytes as fit in the smaller of
+    /// the two. Locks are always taken in the order of the names of the MMFs, the same as for [`lock_write_all`], so
+    /// two copies running in opposite directions can't each end up holding one of the locks, even across processes
+    /// that mapped the views at different addresses. Neither lock is waited on; if either can't be taken, any lock
+    /// already held is released again and the error is returned.
+    pub fn copy_from(&self, src: &Self) -> MMFResult<()> {
+        if self.readonly || self.closed.get() || src.closed.get() || self.map_view.is_none() || src.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
         }
-        Ok(())
+
+        if self.name_str() < src.name_str() {
+            self.lock.lock_write()?;
+            if let Err(e) = src.lock.lock_read() {
+                self.lock.unlock_write()?;
+                return Err(e);
+            }
+        } else {
+            src.lock.lock_read()?;
+            if let Err(e) = self.lock.lock_write() {
+                src.lock.unlock_read()?;
+                return Err(e);
+            }
+        }
+
+        // safety: both views are valid for at least this many bytes, and holding both locks means these are different
+        // MMFs. Views of different MMFs never overlap.
+        let copied = unsafe { guarded_copy(src.write_ptr, self.write_ptr, self.size.min(src.size)) };
+        let src_unlocked = src.lock.unlock_read();
+        let unlocked = self.lock.unlock_write();
+        copied.and(src_unlocked).and(unlocked)
     }
 
-    /// Spinning version of [`read_to_buf`][Self::read_to_buf]
-    fn read_to_buf_spin<F>(&self, buffer: &mut Vec<u8>, count: usize, spinner: Option<F>) -> MMFResult<()>
-    where
-        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
-    {
-        let buf_cap = buffer.capacity();
-        let to_read = if count == 0 { self.size } else { count };
+    /// Borrow part of this MMF as an MMF of its own.
+    ///
+    /// The returned [`SubMmf`] reads and writes `len` bytes starting at `offset` in the data portion of this MMF, using
+    /// the same lock. This allows handing a subsystem only the part of the data it should care about, without mapping
+    /// a second view. Ranges that don't fit in this MMF error with [`NotEnoughMemory`][MMFError::NotEnoughMemory].
+    pub fn subview(&self, offset: usize, len: usize) -> MMFResult<SubMmf<'_, LOCK>> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size => Ok(SubMmf { parent: self, offset, size: len }),
+            _ => Err(MMFError::NotEnoughMemory),
+        }
+    }
 
-        if buf_cap < to_read {
-            buffer.reserve_exact(to_read - buf_cap);
+    /// Use the data as a queue that any number of processes push fixed size messages onto, and a single one pops from.
+    ///
+    /// See [`MpscMmf`] for how it works. Every process must pass the same `slot_size`, the largest message that fits.
+    /// The queue takes up the entire data and never touches the lock, so don't mix it with other ways of reading and
+    /// writing the MMF. Errors with [`MMF_NotFound`][MMFError::MMF_NotFound] if the MMF is readonly or closed, with
+    /// [`UnalignedOffset`][MMFError::UnalignedOffset] if the data isn't 4 byte aligned, and with
+    /// [`NotEnoughMemory`][MMFError::NotEnoughMemory] if not even a single slot fits.
+    pub fn mpsc(&self, slot_size: usize) -> MMFResult<MpscMmf<'_, LOCK>> {
+        if self.readonly || self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
         }
-        unsafe {
-            self.read_to_raw_spin(buffer.as_mut_ptr(), count, spinner)?;
-            buffer.set_len(to_read);
+        if self.write_ptr.align_offset(align_of::<AtomicU32>()) != 0 {
+            return Err(MMFError::UnalignedOffset);
         }
-        Ok(())
+        // Round slots up to keep every sequence aligned.
+        let stride = slot_size
+            .checked_next_multiple_of(size_of::<u32>())
+            .and_then(|size| size.checked_add(MpscMmf::<LOCK>::SLOT_HEADER_SIZE))
+            .ok_or(MMFError::NotEnoughMemory)?;
+        let slots = self.size.saturating_sub(MpscMmf::<LOCK>::HEADER_SIZE) / stride;
+        if slots == 0 {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        // Positions wrap around at `u32::MAX`, which only lines up with the slots for a power of two. Capping it at
+        // 2^31 keeps the distance between a position and a sequence within an `i32`.
+        let capacity = (1 << slots.ilog2()).min(1 << 31);
+        Ok(MpscMmf { mmf: self, slot_size, stride, capacity })
     }
 
-    /// Read into a raw pointer and pray it's valid for `count` bytes.
+    /// Get the offset of the data from the start of the raw mapping.
     ///
-    /// If the count is 0, this operation will exit with a [non-specific error][MMFError::GeneralFailure]. This method
-    /// provides a best effort to ensure that the read portion of the copy is sound by clamping `count` to the MMFs
-    /// size. This prevents, at the very least, UB from reading beyond the end of the MMF. It also ensures the MMF is
-    /// opened and initialized, with the usual errors from [`read`][Self::read] to make these problems known to callers.
+    /// This is [`LOCK_SIZE`], unless the MMF was created with [`new_aligned`][Self::new_aligned] or opened with
+    /// [`open_aligned`][Self::open_aligned], in which case it's rounded up to the requested alignment.
+    pub const fn data_offset(&self) -> usize {
+        self.data_offset
+    }
+
+    /// Get the size of the raw mapping, which is the size of the data plus its [offset][Self::data_offset].
+    pub const fn raw_size(&self) -> usize {
+        self.size + self.data_offset
+    }
+
+    /// Get the size of the data portion as committed by the OS, excluding the lock bytes.
     ///
-    /// # Safety
+    /// The size can't be read back from the [`HANDLE`], so this asks the OS how large the region behind the view is.
+    /// As the OS rounds this up to whole pages, it is at least as large as what the creator allocated, and likely more.
+    pub fn committed_size(&self) -> MMFResult<usize> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let skip = self.view_skip()?;
+        self.map_view
+            .as_ref()
+            .ok_or(MMFError::MMF_NotFound)?
+            .region_size()
+            .map(|size| size.saturating_sub(skip))
+    }
+
+    /// Touch every page of the data region so the OS faults them all in now, rather than on first access.
+    ///
+    /// This reads one byte per [page][system_page_size] under a read lock, so latency-sensitive readers don't get
+    /// page-fault jitter on their first pass over the data. Pages that aren't committed, as for MMFs made with
+    /// [`new_reserved`][Self::new_reserved], are skipped rather than faulted on.
+    pub fn prefault(&self) -> MMFResult<()> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        self.lock.lock_read()?;
+        let touched = self.touch_committed_pages();
+        let unlocked = self.lock.unlock_read();
+        touched.and(unlocked)
+    }
+
+    /// Read one byte from every committed page of the data, going over it one region of equally committed pages at a
+    /// time. The caller holds the lock.
+    fn touch_committed_pages(&self) -> MMFResult<()> {
+        let page_size = system_page_size() as usize;
+        let mut offset = 0;
+        while offset < self.size {
+            // safety: `offset` is within the data region.
+            let region = query_pages(unsafe { self.write_ptr.add(offset) }.cast_const().cast())?;
+            // Regions are whole pages, so every page from `offset` up to here gets touched.
+            let region_end = (region.BaseAddress as usize + region.RegionSize - self.write_ptr as usize).min(self.size);
+            if region.State == MEM_COMMIT {
+                for page in (offset..region_end).step_by(page_size) {
+                    // safety: `page` is within the data region, and volatile reads keep the compiler from skipping it.
+                    guarded(|| unsafe { self.write_ptr.add(page).read_volatile() })?;
+                }
+            }
+            offset = region_end;
+        }
+        Ok(())
+    }
+
+    /// Pin the pages of the view in RAM, so they never get paged out while this process has them locked.
+    ///
+    /// The amount of memory a process can lock is capped by its minimum working set size, so large MMFs may fail with
+    /// an OS error (`ERROR_WORKING_SET_QUOTA`) unless the working set is raised first. That in turn may require the
+    /// `SeIncreaseWorkingSetPrivilege`. Pages stay locked until [`unlock_in_memory`][Self::unlock_in_memory] is called
+    /// or the view is unmapped.
+    pub fn lock_in_memory(&self) -> MMFResult<()> {
+        let (ptr, len) = (self.view_ptr()?, self.view_skip()? + self.size);
+        // Safety: the pointer and size describe the view we own, and microSEH handles the OS side of errors.
+        Ok(try_seh(|| unsafe { VirtualLock(ptr, len) })??)
+    }
+
+    /// Allow the pages of the view to be paged out again after [`lock_in_memory`][Self::lock_in_memory].
+    ///
+    /// Errors if the pages weren't locked.
+    pub fn unlock_in_memory(&self) -> MMFResult<()> {
+        let (ptr, len) = (self.view_ptr()?, self.view_skip()? + self.size);
+        // Safety: the pointer and size describe the view we own, and microSEH handles the OS side of errors.
+        Ok(try_seh(|| unsafe { VirtualUnlock(ptr, len) })??)
+    }
+
+    /// Commit the pages backing `len` bytes of data starting at `offset`, for MMFs made with
+    /// [`new_reserved`][Self::new_reserved].
+    ///
+    /// Whole pages are committed, so a few bytes around the range may come along. Committing pages that already are is
+    /// a no-op, as is an empty range. Errors with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if the range doesn't
+    /// fit in the data, or with the OS error if the system can't back the pages with memory.
+    pub fn commit_range(&self, offset: usize, len: usize) -> MMFResult<()> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size => {}
+            _ => return Err(MMFError::NotEnoughMemory),
+        }
+        if len == 0 {
+            return Ok(());
+        }
+        // safety: the range was checked to be within the data.
+        commit_pages(unsafe { self.write_ptr.add(offset) }.cast(), len)
+    }
+
+    /// Get the amount of bytes in the view before the data, which is the lock for all but ranged MMFs.
+    fn view_skip(&self) -> MMFResult<usize> {
+        Ok(self.write_ptr as usize - self.view_ptr()? as usize)
+    }
+
+    /// Get the start of the view, including the lock bytes, for handing to the OS.
+    fn view_ptr(&self) -> MMFResult<*const std::ffi::c_void> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        Ok(self.map_view.as_ref().ok_or(MMFError::MMF_NotFound)?.as_ptr().cast_const().cast())
+    }
+
+    /// Read a null-terminated string from the start of the MMF, as written by C and friends.
+    ///
+    /// The whole MMF is read under the lock, and the string ends at the first null byte. If there is no null byte, the
+    /// entire MMF is taken to be the string. Errors with [`InvalidUtf8`][MMFError::InvalidUtf8] if it's not UTF-8.
+    pub fn read_cstr(&self) -> MMFResult<String> {
+        String::from_utf8(self.read_until_nul()?).map_err(|_| MMFError::InvalidUtf8)
+    }
+
+    /// Same as [`read_cstr`][Self::read_cstr], but replaces invalid UTF-8 rather than erroring.
+    pub fn read_cstr_lossy(&self) -> MMFResult<String> {
+        Ok(String::from_utf8_lossy(&self.read_until_nul()?).into_owned())
+    }
+
+    /// Write a string followed by a null terminator, zeroing out the rest of the MMF.
+    ///
+    /// Errors with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if the string and its terminator don't fit.
+    /// Everything after the terminator is zeroed so consumers never see a stale tail of a previous, longer string.
+    pub fn write_cstr(&self, s: &str) -> MMFResult<()> {
+        if s.len() >= self.size {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        let mut buf = vec![0; self.size];
+        buf[..s.len()].copy_from_slice(s.as_bytes());
+        self.write(&buf)
+    }
+
+    /// Check whether the MMF starts with exactly `bytes`, without copying anything out of it.
+    ///
+    /// Returns `false` if `bytes` is larger than the MMF. Anything in the MMF past the length of `bytes` is ignored.
+    pub fn equals(&self, bytes: &[u8]) -> MMFResult<bool> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        } else if bytes.len() > self.size {
+            return Ok(false);
+        }
+        self.lock.lock_read()?;
+        // safety: the length was checked against the data region, and the read lock keeps writers out.
+        let equal =
+            guarded(|| unsafe { std::slice::from_raw_parts(self.write_ptr.cast_const(), bytes.len()) } == bytes);
+        let unlocked = self.lock.unlock_read();
+        equal.and_then(|equal| unlocked.map(|()| equal))
+    }
+
+    /// Write `new` at `offset`, but only if the bytes there currently match `expected`, all under the write lock.
+    ///
+    /// Returns whether the bytes were swapped. This is enough to build a small shared state machine on, where a peer
+    /// only moves to the next state if the data is still in the state it expects. `expected` and `new` don't need to
+    /// be the same length, but both must fit in the MMF starting at `offset`, or this errors with
+    /// [`NotEnoughMemory`][MMFError::NotEnoughMemory] before taking the lock.
+    pub fn cas_range(&self, offset: usize, expected: &[u8], new: &[u8]) -> MMFResult<bool> {
+        if self.readonly || self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        let fits = |len: usize| offset.checked_add(len).is_some_and(|end| end <= self.size);
+        if !fits(expected.len()) || !fits(new.len()) {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        self.lock.lock_write()?;
+        // safety: both ranges were checked to fit in the data region, and the write lock keeps everyone else out.
+        let swapped = guarded(|| unsafe {
+            let swap = std::slice::from_raw_parts(self.write_ptr.add(offset).cast_const(), expected.len()) == expected;
+            if swap {
+                new.as_ptr().copy_to(self.write_ptr.add(offset), new.len());
+            }
+            swap
+        });
+        let unlocked = self.lock.unlock_write();
+        swapped.and_then(|swapped| unlocked.map(|()| swapped))
+    }
+
+    /// Set the byte at `offset` to 1 or 0 for `value`, as a flag other processes can check without taking the lock.
+    ///
+    /// The byte is treated as an atomic and stored with sequentially consistent ordering, so anything written before
+    /// setting the flag is visible to whoever sees it set. This is meant for simple signals like "data ready", where
+    /// taking the whole lock would be overkill. Keep flags out of the parts of the data written through the lock.
+    /// Errors with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if `offset` is past the end of the data.
+    pub fn set_flag(&self, offset: usize, value: bool) -> MMFResult<()> {
+        if self.readonly {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let flag = self.flag(offset)?;
+        guarded(|| flag.store(u8::from(value), Ordering::SeqCst))
+    }
+
+    /// Check the flag at `offset`, set through [`set_flag`][Self::set_flag]. Any byte other than 0 counts as set.
+    pub fn get_flag(&self, offset: usize) -> MMFResult<bool> {
+        let flag = self.flag(offset)?;
+        guarded(|| flag.load(Ordering::SeqCst) != 0)
+    }
+
+    /// Wait for the flag at `offset` to be set, for at most `timeout`.
+    ///
+    /// Returns `true` once the flag is seen set, or `false` if it still wasn't when `timeout` passed. This spins with
+    /// the same backoff as [`wait_for_init`][Self::wait_for_init], which makes for a cheap handshake where a producer
+    /// sets a flag and a consumer waits on it. It does burn a core while waiting, so keep the timeouts short.
+    pub fn wait_flag(&self, offset: usize, timeout: Duration) -> MMFResult<bool> {
+        let flag = self.flag(offset)?;
+        poll_until(Instant::now().checked_add(timeout), || guarded(|| flag.load(Ordering::SeqCst) != 0))
+    }
+
+    /// Get the byte at `offset` in the data as an atomic, for the flag functions.
+    fn flag(&self, offset: usize) -> MMFResult<&AtomicU8> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if offset >= self.size {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        // safety: the offset is within the data region, and `AtomicU8` has the same size and alignment as a `u8`.
+        Ok(unsafe { AtomicU8::from_ptr(self.write_ptr.add(offset)) })
+    }
+
+    /// Fill several buffers in order from the start of the MMF, under a single read lock.
+    ///
+    /// Returns the total amount of bytes copied. Reading stops at the end of the MMF, so later buffers may be left
+    /// partially or entirely untouched.
+    pub fn read_vectored(&self, bufs: &mut [&mut [u8]]) -> MMFResult<usize> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        self.lock.lock_read()?;
+        let mut offset = 0;
+        let copied = bufs.iter_mut().try_for_each(|buf| {
+            let count = buf.len().min(self.size - offset);
+            // safety: `offset + count` never exceeds the data region, and `count` fits in the buffer.
+            let copied = unsafe { guarded_copy(self.write_ptr.add(offset), buf.as_mut_ptr(), count) };
+            offset += count;
+            copied
+        });
+        let unlocked = self.lock.unlock_read();
+        copied.and(unlocked).map(|()| offset)
+    }
+
+    /// Set every byte of the data to `byte`, under the write lock.
+    pub fn fill(&self, byte: u8) -> MMFResult<()> {
+        if self.readonly || self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        self.lock.lock_write()?;
+        // safety: this covers exactly the data region, and the write lock keeps everyone else out.
+        let filled = guarded(|| unsafe { self.write_ptr.write_bytes(byte, self.size) });
+        let unlocked = self.lock.unlock_write();
+        filled.and(unlocked)
+    }
+
+    /// Try to write to the MMF once, without spinning, telling contention apart from actual errors.
+    ///
+    /// Returns `Ok(false)` if someone else is holding the lock right now, so you can try again later, and `Ok(true)` if
+    /// the data was written. Errors are reserved for problems retrying won't fix, like the data not fitting.
+    pub fn try_write(&self, buffer: &[u8]) -> MMFResult<bool> {
+        match self.write(buffer) {
+            Ok(()) => Ok(true),
+            Err(MMFError::ReadLocked | MMFError::WriteLocked | MMFError::LockViolation) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write several buffers back to back into the MMF, under a single write lock.
+    ///
+    /// This saves concatenating e.g. a header and a payload into one buffer first. Errors with
+    /// [`NotEnoughMemory`][MMFError::NotEnoughMemory] if the combined length doesn't fit, before writing anything.
+    pub fn write_vectored(&self, bufs: &[&[u8]]) -> MMFResult<()> {
+        if self.readonly || self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let total = bufs.iter().try_fold(0usize, |total, buf| total.checked_add(buf.len()));
+        if total.map_or(true, |total| total > self.size) {
+            Err(MMFError::NotEnoughMemory)
+        } else if !self.lock.initialized() {
+            Err(MMFError::Uninitialized)
+        } else {
+            self.lock.lock_write()?;
+            let mut offset = 0;
+            let copied = bufs.iter().try_for_each(|buf| {
+                // safety: the combined length was checked to fit in the data region.
+                let copied = unsafe { guarded_copy(buf.as_ptr(), self.write_ptr.add(offset), buf.len()) };
+                offset += buf.len();
+                copied
+            });
+            let unlocked = self.lock.unlock_write();
+            copied.and(unlocked)
+        }
+    }
+
+    /// Write to the MMF without touching the lock at all.
+    ///
+    /// This skips the CAS loops in [`lock_write`][MMFLock::lock_write] and [`unlock_write`][MMFLock::unlock_write],
+    /// which matters for a single producer pushing out a lot of writes. All other checks from [`write`][Mmf::write]
+    /// still apply.
+    ///
+    /// # Safety
+    /// The caller must guarantee nobody else is writing to this MMF for the duration of the call, in this process or
+    /// any other. Readers that don't validate what they read (e.g. through a [`SeqLock`]) may see torn data.
+    #[cfg(feature = "unlocked_write")]
+    pub unsafe fn write_unlocked(&self, buffer: &[u8]) -> MMFResult<()> {
+        if self.readonly || self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        if buffer.len() > self.size {
+            Err(MMFError::NotEnoughMemory)
+        } else if !self.lock.initialized() {
+            Err(MMFError::Uninitialized)
+        } else {
+            guarded_copy(buffer.as_ptr(), self.write_ptr, buffer.len())
+        }
+    }
+
+    /// Get a slice over the data, without touching the lock.
+    ///
+    /// This is the escape hatch for building your own abstractions on top of the mapping.
+    ///
+    /// # Safety
+    /// The caller must make sure nobody writes to the MMF for as long as the slice lives, in this process or any other.
+    /// A concurrent writer makes this UB. The slice must also not outlive the view, so don't close the MMF while using
+    /// it.
+    #[cfg(feature = "raw_access")]
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        std::slice::from_raw_parts(self.write_ptr.cast_const(), self.size)
+    }
+
+    /// Get a mutable slice over the data, without touching the lock.
+    ///
+    /// # Safety
+    /// The caller must make sure this is the only access to the MMF for as long as the slice lives, in this process or
+    /// any other. That includes other slices from this function and [`as_slice`][Self::as_slice]; any aliasing or
+    /// concurrent access makes this UB. The slice must also not outlive the view, so don't close the MMF while using
+    /// it.
+    #[cfg(feature = "raw_access")]
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn as_mut_slice(&self) -> &mut [u8] {
+        std::slice::from_raw_parts_mut(self.write_ptr, self.size)
+    }
+
+    /// Get a reference to a `T` at the start of the data without copying, holding the read lock until it's dropped.
+    ///
+    /// Errors with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if a `T` doesn't fit in the MMF, and with
+    /// [`UnalignedOffset`][MMFError::UnalignedOffset] if the data isn't aligned for a `T`. Views are page aligned and
+    /// the data starts [`LOCK_SIZE`] bytes in, so types aligned to more than 4 bytes will generally be refused. Create
+    /// the MMF with [`new_aligned`][Self::new_aligned] to store those. The value is read once up front, so a value on
+    /// pages that aren't committed errors with [`MMF_NotFound`][MMFError::MMF_NotFound] rather than faulting later.
+    ///
+    /// # Safety
+    /// Nobody may write to the MMF for as long as the guard lives, in this process or any other. Writers going through
+    /// the lock are kept out by it, but those that skip it aren't. Neither are any writers for lock types that let
+    /// readers and writers overlap, like [`SeqLock`], so those must not be written to at all while the guard lives.
+    #[cfg(feature = "raw_access")]
+    pub unsafe fn as_typed<T: Pod>(&self) -> MMFResult<TypedReadGuard<'_, LOCK, T>> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        if size_of::<T>() > self.size {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        if self.write_ptr.align_offset(align_of::<T>()) != 0 {
+            return Err(MMFError::UnalignedOffset);
+        }
+        self.lock.lock_read()?;
+        let value = self.write_ptr.cast_const().cast::<T>();
+        // safety: the value fits in the data and is aligned, and `T: Pod` makes any bytes there a valid `T`.
+        if let Err(e) = guarded(|| unsafe { value.read_volatile() }) {
+            _ = self.lock.unlock_read();
+            return Err(e);
+        }
+        Ok(TypedReadGuard { mmf: self, value: &*value })
+    }
+
+    /// Serialize a value into the MMF, prefixed with its length so readers know how many bytes are valid.
+    ///
+    /// The value is serialized using [`postcard`], and the first 4 bytes of the data hold the length as a little
+    /// endian `u32`. Errors with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if that doesn't fit in the MMF.
+    #[cfg(feature = "serde")]
+    pub fn write_serde<T: Serialize>(&self, val: &T) -> MMFResult<()> {
+        let data = postcard::to_stdvec(val).map_err(|_| MMFError::Serialization)?;
+        let len = u32::try_from(data.len()).map_err(|_| MMFError::NotEnoughMemory)?;
+        if data.len() + 4 > self.size {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        let mut buf = Vec::with_capacity(data.len() + 4);
+        buf.extend_from_slice(&len.to_le_bytes());
+        buf.extend_from_slice(&data);
+        self.write(&buf)
+    }
+
+    /// Deserialize a value written by [`write_serde`][Self::write_serde].
+    ///
+    /// Errors with [`Serialization`][MMFError::Serialization] if the length prefix doesn't fit in the MMF, or if the
+    /// data is not a valid `T`.
+    #[cfg(feature = "serde")]
+    pub fn read_serde<T: DeserializeOwned>(&self) -> MMFResult<T> {
+        let buf = self.read(0)?;
+        let prefix = buf.get(..4).ok_or(MMFError::Serialization)?;
+        let len = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        let data = buf.get(4..).and_then(|d| d.get(..len)).ok_or(MMFError::Serialization)?;
+        postcard::from_bytes(data).map_err(|_| MMFError::Serialization)
+    }
+
+    /// Read the entire MMF and cut it off at the first null byte, if any.
+    fn read_until_nul(&self) -> MMFResult<Vec<u8>> {
+        let mut buf = self.read(0)?;
+        if let Some(end) = buf.iter().position(|&b| b == 0) {
+            buf.truncate(end);
+        }
+        Ok(buf)
+    }
+
+    /// Get the raw value of the OS handle to the MMF, for logging and diagnostics.
+    ///
+    /// This is the same number tools like Process Explorer show for the handle. It can't be used to do anything with
+    /// the MMF from safe code, but it's nice for correlating log lines.
+    pub fn handle_value(&self) -> isize {
+        self.handle.get().0 as isize
+    }
+
+    /// Get the namespace of the file, if any. If an empty string is returned, it's Local.
+    pub fn namespace(&self) -> String {
+        self.namespace_str().to_owned()
+    }
+
+    /// Return the filename the MMF is bound to, which is only the whole name if no namespace is provided.
+    pub fn filename(&self) -> String {
+        self.filename_str().to_owned()
+    }
+
+    /// Returns the stored name, which should be `[Namespace\]<FileName>`
+    pub fn fullname(&self) -> String {
+        self.name_str().to_owned()
+    }
+
+    /// Borrowing version of [`namespace`][Self::namespace], for when allocating on every call is too much.
+    pub fn namespace_str(&self) -> &str {
+        self.name.split_once('\\').unwrap_or_default().0
+    }
+
+    /// Borrowing version of [`filename`][Self::filename], for when allocating on every call is too much.
+    pub fn filename_str(&self) -> &str {
+        self.name.split_once('\\').map_or(self.name_str(), |s| s.1)
+    }
+
+    /// Borrowing version of [`fullname`][Self::fullname], for when allocating on every call is too much.
+    pub fn name_str(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Close the MMF. Don't worry about calling this, it's handled in [`Drop`].
+    pub fn close(&self) -> MMFResult<()> {
+        self.closed.set(true);
+        // Safety: microSEH handles the OS side of this error, and the match handles this end.
+        match try_seh(|| unsafe { CloseHandle(self.handle.get()) })?.map_err(MMFError::from) {
+            Err(MMFError::OS_OK(_)) | Ok(_) => Ok(()),
+            err => err.map_err(|e| {
+                eprintln!("Error closing MMF's handle: {:#?}", e);
+                e
+            }),
+        }
+    }
+
+    /// Best-effort check whether this is the last handle to the MMF, meaning closing it destroys the data.
+    ///
+    /// The OS doesn't tell how many handles to a mapping exist, so this closes the handle and reopens the MMF by name.
+    /// The name goes away together with the last handle, so failing to reopen it with
+    /// [`MMF_NotFound`][MMFError::MMF_NotFound] means this was the last one. In that case this MMF stays closed, as
+    /// nobody can open it anymore anyway.
+    ///
+    /// This is inherently racy. Peers opening the MMF while the handle is closed won't find it, peers closing theirs
+    /// right after this returns `false` make this the last handle anyway, and someone creating an MMF with the same
+    /// name in between means this reopens _theirs_ rather than the one mapped here. Only base cleanup decisions on this
+    /// when you control the processes involved. Errors other than not finding the MMF also leave it closed. The MMF is
+    /// reopened with the same access it was originally opened with, so readonly MMFs don't suddenly need write access.
+    pub fn is_last_handle(&self) -> MMFResult<bool> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        self.close()?;
+        let mmf_name = PCSTR::from_raw(self.name.to_ptr());
+        // Safety: microSEH handles the OS side of errors, and the match handles this end.
+        match try_seh(|| unsafe { OpenFileMappingA(self.access.0, false, mmf_name) })?.map_err(MMFError::from) {
+            Ok(handle) => {
+                self.handle.set(handle);
+                self.closed.set(false);
+                Ok(false)
+            }
+            Err(MMFError::MMF_NotFound) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Release any locks still held through this instance, as long as the lock is still mapped.
+    fn release_held_locks(&self) {
+        if self.map_view.is_some() {
+            if self.lock.holds_write_lock() {
+                _ = self.lock.unlock_write();
+            }
+            for _ in 0..self.lock.reader_guard_count() {
+                if self.lock.unlock_read().is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Explicitly tear down the MMF, unmapping the view and closing the handle.
+    ///
+    /// Unlike [`Drop`], which swallows any errors, this reports the first error encountered. Both steps are always
+    /// attempted, so a failure to unmap the view does not leak the handle.
+    pub fn teardown(mut self) -> MMFResult<()> {
+        self.release_held_locks();
+        let unmapped = self.map_view.take().map(|view| ManuallyDrop::new(view).unmap()).unwrap_or(Ok(()));
+        let unmapped_lock = self.lock_view.take().map(|view| ManuallyDrop::new(view).unmap()).unwrap_or(Ok(()));
+        let closed = self.close();
+        unmapped.and(unmapped_lock).and(closed)
+    }
+}
+
+/// Get the granularity for the starting offset of mapped views, which is 64KiB on pretty much every system.
+///
+/// `MapViewOfFile` refuses offsets that aren't a multiple of this. The value is fetched from the OS once and cached.
+#[cfg(feature = "impl_mmf")]
+pub fn system_allocation_granularity() -> u32 {
+    /// The cached granularity, it can't change while the system is running.
+    static GRANULARITY: OnceLock<u32> = OnceLock::new();
+    *GRANULARITY.get_or_init(|| system_info().dwAllocationGranularity)
+}
+
+/// Get the size of a memory page, which is 4KiB on pretty much every system.
+///
+/// The value is fetched from the OS once and cached.
+#[cfg(feature = "impl_mmf")]
+pub fn system_page_size() -> u32 {
+    /// The cached page size, it can't change while the system is running.
+    static PAGE_SIZE: OnceLock<u32> = OnceLock::new();
+    *PAGE_SIZE.get_or_init(|| system_info().dwPageSize)
+}
+
+/// Ask the OS for info on the system.
+#[cfg(feature = "impl_mmf")]
+fn system_info() -> SYSTEM_INFO {
+    let mut info = SYSTEM_INFO::default();
+    // Safety: `info` is a valid out pointer and this function can't fail.
+    unsafe { GetSystemInfo(&mut info) };
+    info
+}
+
+/// Commit the pages covering `len` bytes from `address`, which must be in a view of a reserved MMF.
+#[cfg(feature = "impl_mmf")]
+fn commit_pages(address: *mut std::ffi::c_void, len: usize) -> MMFResult<()> {
+    // Safety: `VirtualAlloc` returns a NULL pointer on failure, which is checked right after.
+    let committed = try_seh(|| unsafe { VirtualAlloc(Some(address.cast_const()), len, MEM_COMMIT, PAGE_READWRITE) })?;
+    if committed.is_null() {
+        Err(WErr::from_win32().into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Ask the OS for info on the region of pages with the same state and protection, starting at the page of `address`.
+#[cfg(feature = "impl_mmf")]
+fn query_pages(address: *const std::ffi::c_void) -> MMFResult<MEMORY_BASIC_INFORMATION> {
+    let mut info = MEMORY_BASIC_INFORMATION::default();
+    // Safety: `info` is a valid out pointer of the size we pass, and microSEH handles the OS side of errors.
+    let written = try_seh(|| unsafe { VirtualQuery(Some(address), &mut info, size_of::<MEMORY_BASIC_INFORMATION>()) })?;
+    if written == 0 {
+        Err(WErr::from_win32().into())
+    } else {
+        Ok(info)
+    }
+}
+
+/// Run `f`, turning any exception raised while it touches the data of an MMF into an error.
+///
+/// Access violations and failed page-ins mean the memory behind a view went away or was never committed, which gets
+/// reported as [`MMF_NotFound`][MMFError::MMF_NotFound]. Any other exception is converted as usual. The lock is none
+/// of this function's business, callers still need to release it whether `f` worked out or not. Every access to the
+/// data goes through here, so a reserved MMF with uncommitted pages never takes the process down.
+#[cfg(feature = "impl_mmf")]
+fn guarded<R>(f: impl FnMut() -> R) -> MMFResult<R> {
+    try_seh(f).map_err(|e| match e.code() {
+        ExceptionCode::AccessViolation | ExceptionCode::InPageError => MMFError::MMF_NotFound,
+        _ => e.into(),
+    })
+}
+
+/// Copy `count` bytes from `src` to `dst` through [`guarded`].
+///
+/// # Safety
+/// See [`std::ptr::copy`]. Faults are caught rather than taking the process down, but that doesn't make any pointer
+/// valid. It only turns the "OS unwinds your ass" scenario into an error.
+#[cfg(feature = "impl_mmf")]
+unsafe fn guarded_copy(src: *const u8, dst: *mut u8, count: usize) -> MMFResult<()> {
+    guarded(|| unsafe { src.copy_to(dst, count) })
+}
+
+/// Clamp the length of a view to the `size` of the data it views, for MMFs sized independently of the pointer size.
+#[cfg(feature = "impl_mmf")]
+fn clamp_view(size: NonZeroU64, view_len: NonZeroUsize) -> NonZeroUsize {
+    // If `size` doesn't fit in a `usize`, it's larger than any view.
+    usize::try_from(size.get())
+        .ok()
+        .and_then(NonZeroUsize::new)
+        .map_or(view_len, |size| size.min(view_len))
+}
+
+/// Widen the size of an MMF to the 64 bits the OS takes it as.
+///
+/// `usize` is at most 64 bits on Windows, so this never fails. The error only exists because the standard library
+/// can't know that, and maps to [`NotEnoughMemory`][MMFError::NotEnoughMemory] like other sizes that don't fit.
+#[cfg(feature = "impl_mmf")]
+fn size_u64(size: NonZeroUsize) -> MMFResult<NonZeroU64> {
+    NonZeroU64::try_from(size).map_err(|_| MMFError::NotEnoughMemory)
+}
+
+/// Build the full name of an MMF, prefixing `name` with the namespace.
+///
+/// Names that don't fit in a [`ztr64`] error with [`NameTooLong`][MMFError::NameTooLong] rather than getting cut off,
+/// as two processes cutting off different long names could end up sharing an MMF, or the same name could end up
+/// meaning different MMFs.
+#[cfg(feature = "impl_mmf")]
+fn full_name(name: &str, namespace: Namespace) -> MMFResult<ztr64> {
+    let prefix = namespace.prefix();
+    // One of the 64 bytes goes to the terminating null.
+    if prefix.len() + name.len() > 63 {
+        return Err(MMFError::NameTooLong);
+    }
+    Ok(ztr64::make(&format!("{prefix}{name}")))
+}
+
+/// Get the offset of data aligned to `align` bytes, which must be a power of two no larger than the page size.
+#[cfg(feature = "impl_mmf")]
+fn aligned_data_offset(align: usize) -> MMFResult<usize> {
+    if !align.is_power_of_two() || align > system_page_size() as usize {
+        return Err(MMFError::UnalignedOffset);
+    }
+    // The lock size is a power of two as well, so the larger of the two is a multiple of both.
+    Ok(LOCK_SIZE.max(align))
+}
+
+/// Round an offset down to the nearest multiple of [the system allocation granularity][system_allocation_granularity].
+///
+/// Map from the returned offset and skip the difference to get to the data you wanted.
+#[cfg(feature = "impl_mmf")]
+pub fn align_offset(offset: u64) -> u64 {
+    let granularity = u64::from(system_allocation_granularity());
+    offset - offset % granularity
+}
+
+/// Creating MMFs with a lock picked at runtime.
+#[cfg(all(feature = "impl_mmf", feature = "impl_lock"))]
+impl MemoryMappedFile<DynLock> {
+    /// Create a new MMF with the lock built by `make_lock`, without having to be generic over the type of lock.
+    ///
+    /// `make_lock` gets a pointer to the freshly zeroed lock bytes at the start of the mapping, and should build the
+    /// lock there like [`from_raw`][MMFLock::from_raw] does, e.g. `|ptr| unsafe { SeqLock::from_raw(ptr) }`. The lock
+    /// is initialized afterwards. Otherwise this works exactly like [`new`][Self::new].
+    pub fn new_with_lock<L: MMFLock + Send + Sync + 'static>(
+        size: NonZeroUsize,
+        name: &str,
+        namespace: Namespace,
+        make_lock: impl FnOnce(*mut u8) -> L,
+    ) -> MMFResult<Self> {
+        Self::create(size, None, name, namespace, LOCK_SIZE, PAGE_READWRITE, false, |ptr| -> DynLock {
+            Box::new(make_lock(ptr))
+        })
+    }
+}
+
+/// Reading for MMFs guarded by a [`SeqLock`].
+#[cfg(all(feature = "impl_mmf", feature = "impl_lock"))]
+impl MemoryMappedFile<SeqLock<'_>> {
+    /// Read data into a buffer without taking a lock, retrying until the read wasn't torn by a concurrent write.
+    ///
+    /// Counts and buffer growth work the same as they do for [`read_to_buf`][Mmf::read_to_buf]. Readers never block
+    /// writers, they spin while a write is in progress and copy the data again if a write raced them.
+    pub fn read_seqlock(&self, buffer: &mut Vec<u8>, count: usize) -> MMFResult<()> {
+        self.read_seqlock_sequence(buffer, count, usize::MAX).map(|_| ())
+    }
+
+    /// Read a snapshot of the data that no write tore, copying it again up to `max_tries` times if one did.
+    ///
+    /// This is the bounded form of [`read_seqlock`][Self::read_seqlock] for callers that would rather give up than
+    /// keep chasing a busy writer. Running out of tries errors with [`MaxTriesReached`][MMFError::MaxTriesReached].
+    /// A snapshot is only as consistent as the writes are: a writer that updates a multi-part structure through several
+    /// writes lets readers in between them. Have it write all parts at once with
+    /// [`write_vectored`][MemoryMappedFile::write_vectored] instead.
+    pub fn read_consistent(&self, count: usize, max_tries: usize) -> MMFResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.read_seqlock_sequence(&mut buf, count, max_tries)?;
+        Ok(buf)
+    }
+
+    /// Watch the MMF for changes, polling the sequence every `poll` and yielding the data each time it changed.
+    ///
+    /// The returned iterator blocks the thread it's used on, sleeping between polls. The first call yields the current
+    /// data right away. Iteration ends once this MMF is closed, any other errors are yielded as they happen.
+    pub fn watch(&self, poll: Duration) -> ChangeIter<'_, '_> {
+        ChangeIter { mmf: self, poll, last: None }
+    }
+
+    /// Stream a payload that's larger than the MMF through it, one chunk per generation of the sequence.
+    ///
+    /// See [`ChunkWriter`] for how chunks are laid out, and pair this with [`watch`][Self::watch] on the reading end.
+    pub fn chunk_writer(&self) -> ChunkWriter<'_, '_> {
+        ChunkWriter { mmf: self, chunks: 0 }
+    }
+
+    /// Does the actual reading for [`read_seqlock`][Self::read_seqlock], returning the sequence the data belongs to.
+    ///
+    /// Gives up with [`MaxTriesReached`][MMFError::MaxTriesReached] once `max_tries` copies were all torn.
+    fn read_seqlock_sequence(&self, buffer: &mut Vec<u8>, count: usize, max_tries: usize) -> MMFResult<u32> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let to_read = if count == 0 { self.size } else { count.min(self.size) };
+
+        if buffer.capacity() < to_read {
+            buffer.reserve_exact(to_read - buffer.len());
+        }
+        let mut tries = 0;
+        let seq = loop {
+            if tries == max_tries {
+                return Err(MMFError::MaxTriesReached);
+            }
+            tries += 1;
+            let seq = self.lock.begin_read()?;
+            // safety: the buffer was grown to fit `to_read` bytes, which is clamped to the size of the MMF.
+            unsafe { guarded_copy(self.write_ptr, buffer.as_mut_ptr(), to_read)? };
+            if self.lock.validate_read(seq) {
+                break seq;
+            }
+        };
+        // safety: the last copy was not torn, and all `to_read` bytes are initialized.
+        unsafe { buffer.set_len(to_read) };
+        Ok(seq)
+    }
+}
+
+/// A blocking iterator over changes to an MMF guarded by a [`SeqLock`].
+///
+/// Create these through [`MemoryMappedFile::watch`].
+#[cfg(all(feature = "impl_mmf", feature = "impl_lock"))]
+#[derive(Debug)]
+pub struct ChangeIter<'a, 'b> {
+    /// The MMF being watched.
+    mmf: &'a MemoryMappedFile<SeqLock<'b>>,
+    /// How long to sleep between polls.
+    poll: Duration,
+    /// The sequence of the last data yielded, if any.
+    last: Option<u32>,
+}
+
+/// Polls the sequence until it changes, then reads a fresh copy of the data.
+#[cfg(all(feature = "impl_mmf", feature = "impl_lock"))]
+impl Iterator for ChangeIter<'_, '_> {
+    /// A fresh copy of the data, or whatever error reading it produced.
+    type Item = MMFResult<Vec<u8>>;
+
+    /// Block until the sequence changes, or the MMF gets closed.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.mmf.closed.get() {
+                return None;
+            }
+            match self.mmf.lock.begin_read() {
+                Ok(seq) if Some(seq) == self.last => sleep(self.poll),
+                Ok(_) => {
+                    let mut buf = Vec::with_capacity(self.mmf.size);
+                    return Some(self.mmf.read_seqlock_sequence(&mut buf, 0, usize::MAX).map(|seq| {
+                        self.last = Some(seq);
+                        buf
+                    }));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Streams a payload through an MMF guarded by a [`SeqLock`], overwriting the data with one chunk at a time.
+///
+/// Every chunk is written prefixed with its length as a little endian `u32`, so readers know how much of the data
+/// belongs to it, and each write bumps the sequence. Readers pick up every generation through
+/// [`watch`][MemoryMappedFile::watch] and strip the prefix. Nothing stops the writer from overwriting a chunk nobody
+/// read yet, so pace the writes to the consumer. Create these through [`MemoryMappedFile::chunk_writer`].
+#[cfg(all(feature = "impl_mmf", feature = "impl_lock"))]
+#[derive(Debug)]
+pub struct ChunkWriter<'a, 'b> {
+    /// The MMF the chunks are written to.
+    mmf: &'a MemoryMappedFile<SeqLock<'b>>,
+    /// The amount of chunks written so far.
+    chunks: usize,
+}
+
+#[cfg(all(feature = "impl_mmf", feature = "impl_lock"))]
+impl ChunkWriter<'_, '_> {
+    /// The amount of bytes the length prefix of every chunk takes up.
+    pub const PREFIX_SIZE: usize = size_of::<u32>();
+
+    /// Get the largest chunk that fits, which is the size of the MMF minus the length prefix.
+    pub fn chunk_capacity(&self) -> usize {
+        self.mmf.size.saturating_sub(Self::PREFIX_SIZE)
+    }
+
+    /// Get the amount of chunks written so far.
+    pub fn chunks_written(&self) -> usize {
+        self.chunks
+    }
+
+    /// Overwrite the data with `chunk` and its length prefix, under the write lock.
+    ///
+    /// Errors with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if the chunk is larger than
+    /// [`chunk_capacity`][Self::chunk_capacity], without writing anything.
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> MMFResult<()> {
+        let len = u32::try_from(chunk.len()).map_err(|_| MMFError::NotEnoughMemory)?;
+        self.mmf.write_vectored(&[&len.to_le_bytes(), chunk])?;
+        self.chunks += 1;
+        Ok(())
+    }
+}
+
+/// Open a family of related MMFs together, or not at all.
+///
+/// Every spec is passed to [`MemoryMappedFile::open`] in order. If any of them fails to open, the ones opened before
+/// it are dropped (and thus closed) and the error is returned. The resulting [`Vec`] is in the same order as `specs`.
+#[cfg(feature = "impl_mmf")]
+pub fn open_all<LOCK: MMFLock>(
+    specs: &[(NonZeroUsize, &str, Namespace, bool)],
+) -> MMFResult<Vec<MemoryMappedFile<LOCK>>> {
+    specs
+        .iter()
+        .map(|&(size, name, namespace, readonly)| MemoryMappedFile::open(size, name, namespace, readonly))
+        .collect()
+}
+
+/// Take the write lock on several MMFs at once, for updating them together without anyone seeing a partial update.
+///
+/// Locks are taken in the order of the MMFs' full names rather than the order they're passed in, so any processes
+/// locking the same set of MMFs this way can't deadlock on each other. Contention is waited out with a small backoff
+/// until `timeout` passes, after which this errors with [`MaxTriesReached`][MMFError::MaxTriesReached]. On any error,
+/// the locks taken so far are released again. Passing the same MMF twice, or two MMFs with the same name, can never
+/// succeed as the second lock waits on the first.
+///
+/// The locks are held until the returned [`MultiWriteGuard`] is dropped, write through it in the meantime.
+#[cfg(feature = "impl_mmf")]
+pub fn lock_write_all<'a, LOCK: MMFLock>(
+    mmfs: &[&'a MemoryMappedFile<LOCK>],
+    timeout: Duration,
+) -> MMFResult<MultiWriteGuard<'a, LOCK>> {
+    // A timeout too large to add up is as good as none at all.
+    let deadline = Instant::now().checked_add(timeout);
+    let mut order: Vec<usize> = (0..mmfs.len()).collect();
+    order.sort_by(|&a, &b| mmfs[a].name_str().cmp(mmfs[b].name_str()));
+
+    // Dropping the guard on an early return releases whatever it holds by then.
+    let mut guard = MultiWriteGuard { mmfs: mmfs.to_vec(), held: Vec::with_capacity(mmfs.len()) };
+    for idx in order {
+        let mmf = mmfs[idx];
+        if mmf.readonly || mmf.closed.get() || mmf.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        } else if !mmf.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        lock_until(deadline, || mmf.lock.lock_write())?;
+        guard.held.push(idx);
+    }
+    Ok(guard)
+}
+
+/// Keep trying to take a lock until it works, or `deadline` passes while it's contended.
+///
+/// Waits through [`poll_until`]. Running out of time errors with [`MaxTriesReached`][MMFError::MaxTriesReached], other
+/// errors are returned right away.
+#[cfg(feature = "impl_mmf")]
+fn lock_until(deadline: Option<Instant>, mut lock: impl FnMut() -> MMFResult<()>) -> MMFResult<()> {
+    let locked = poll_until(deadline, || match lock() {
+        Err(MMFError::ReadLocked | MMFError::WriteLocked | MMFError::LockViolation) => Ok(false),
+        res => res.map(|()| true),
+    })?;
+    if locked {
+        Ok(())
+    } else {
+        Err(MMFError::MaxTriesReached)
+    }
+}
+
+/// Call `check` until it returns `true`, or `deadline` passes, returning whether it got there in time.
+///
+/// In between, this spins twice as long every try up to 64 spins, and yields the thread from there on. A deadline of
+/// `None` waits forever, which is what timeouts too large to add up to an [`Instant`] get. Errors from `check` are
+/// returned right away.
+#[cfg(feature = "impl_mmf")]
+fn poll_until(deadline: Option<Instant>, mut check: impl FnMut() -> MMFResult<bool>) -> MMFResult<bool> {
+    let mut backoff = 1_u32;
+
+    loop {
+        if check()? {
+            return Ok(true);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(false);
+        }
+
+        if backoff <= 64 {
+            (0..backoff).for_each(|_| spin_loop());
+            backoff <<= 1;
+        } else {
+            yield_now();
+        }
+    }
+}
+
+/// Write locks on several MMFs, held until this is dropped.
+///
+/// Create these through [`lock_write_all`]. MMFs are referred to by their index in the slice passed in there.
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct MultiWriteGuard<'a, LOCK: MMFLock> {
+    /// The MMFs, in the order they were passed in.
+    mmfs: Vec<&'a MemoryMappedFile<LOCK>>,
+    /// Indices of the MMFs whose lock is held, in the order they were taken.
+    held: Vec<usize>,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> MultiWriteGuard<'_, LOCK> {
+    /// Write to the MMF at `index`, without touching the lock this guard already holds.
+    ///
+    /// Errors with [`MMF_NotFound`][MMFError::MMF_NotFound] if there's no MMF at `index`, or it was closed since, and
+    /// with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if the data doesn't fit.
+    pub fn write(&self, index: usize, buffer: &[u8]) -> MMFResult<()> {
+        let mmf = self.mmfs.get(index).ok_or(MMFError::MMF_NotFound)?;
+        if mmf.closed.get() {
+            Err(MMFError::MMF_NotFound)
+        } else if buffer.len() > mmf.size {
+            Err(MMFError::NotEnoughMemory)
+        } else {
+            // safety: the buffer fits in the data region, and this guard holds the write lock.
+            unsafe { guarded_copy(buffer.as_ptr(), mmf.write_ptr, buffer.len()) }
+        }
+    }
+}
+
+/// Release the locks in the opposite order they were taken.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Drop for MultiWriteGuard<'_, LOCK> {
+    /// Unlock every MMF this guard holds, ignoring errors as there's nobody to report them to.
+    fn drop(&mut self) {
+        for &idx in self.held.iter().rev() {
+            _ = self.mmfs[idx].lock.unlock_write();
+        }
+    }
+}
+
+/// Types that can be read straight from the bytes of an MMF, because every bit pattern of their size is valid.
+///
+/// This is implemented for the integer and float primitives, and for arrays of anything implementing it. Implement it
+/// for your own `#[repr(C)]` types made up of `Pod` fields to use them with [`as_typed`][MemoryMappedFile::as_typed].
+///
+/// # Safety
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid `Self`. That rules out padding, `bool`, `char`,
+/// enums, references and anything else with invalid values. Pointers are technically fine, but pointing into another
+/// process' memory makes them useless at best.
+#[cfg(feature = "raw_access")]
+pub unsafe trait Pod: Copy + 'static {}
+
+/// Implement [`Pod`] for primitives that are valid for any bit pattern.
+#[cfg(feature = "raw_access")]
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+#[cfg(feature = "raw_access")]
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+/// Arrays have no padding between their elements, so they're valid for any bit pattern if their elements are.
+#[cfg(feature = "raw_access")]
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// A reference to the `T` at the start of an MMF's data, holding its read lock until this is dropped.
+///
+/// Create these through [`MemoryMappedFile::as_typed`], and use them through [`Deref`].
+#[cfg(feature = "raw_access")]
+#[derive(Debug)]
+pub struct TypedReadGuard<'a, LOCK: MMFLock, T: Pod> {
+    /// The MMF whose read lock this holds.
+    mmf: &'a MemoryMappedFile<LOCK>,
+    /// The value at the start of the data.
+    value: &'a T,
+}
+
+#[cfg(feature = "raw_access")]
+impl<LOCK: MMFLock, T: Pod> Deref for TypedReadGuard<'_, LOCK, T> {
+    type Target = T;
+
+    /// Get the value, which can't be written to through the lock for as long as this guard lives.
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+/// Release the read lock once the reference is gone.
+#[cfg(feature = "raw_access")]
+impl<LOCK: MMFLock, T: Pod> Drop for TypedReadGuard<'_, LOCK, T> {
+    /// Unlock the MMF, ignoring errors as there's nobody to report them to.
+    fn drop(&mut self) {
+        _ = self.mmf.lock.unlock_read();
+    }
+}
+
+/// A bounded queue in an MMF, where any number of processes push messages and a single one pops them.
+///
+/// The data starts with two `u32` counters, the position of the head the consumer pops from and that of the tail
+/// producers push to. Those are followed by [`capacity`][Self::capacity] slots, each holding a `u32` sequence, the
+/// `u32` length of the message and [`slot_size`][Self::slot_size] bytes for the message itself. Producers claim a
+/// position by bumping the tail with a compare-and-swap, fill the slot and then publish it by bumping its sequence.
+/// The consumer pops a slot once it's published, and bumps its sequence again to hand it back to producers for the
+/// next lap. Sequences are stored relative to the slot's index, so an MMF that's all zeroes is an empty queue and a
+/// freshly created one is ready to go. Only the consumer ever touches the head, so it can pick up where it left off.
+///
+/// Producers never wait on each other for longer than a compare-and-swap, and nobody takes the MMF's lock. Popping
+/// from more than one thread or process at a time is a logic error though, and has consumers pop the same message.
+/// A producer that dies between claiming and publishing a slot blocks the consumer at that slot for good.
+///
+/// Create these through [`MemoryMappedFile::mpsc`].
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct MpscMmf<'a, LOCK: MMFLock> {
+    /// The MMF holding the queue.
+    mmf: &'a MemoryMappedFile<LOCK>,
+    /// The largest message that fits in a slot.
+    slot_size: usize,
+    /// The distance between two slots, the slot size rounded up plus the slot header.
+    stride: usize,
+    /// The amount of slots, always a power of two.
+    capacity: usize,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> MpscMmf<'_, LOCK> {
+    /// The amount of bytes the head and tail at the start of the data take up.
+    pub const HEADER_SIZE: usize = 2 * size_of::<u32>();
+    /// The amount of bytes the sequence and length at the start of every slot take up.
+    pub const SLOT_HEADER_SIZE: usize = 2 * size_of::<u32>();
+
+    /// Get the amount of messages the queue holds when full.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the largest message that fits in the queue.
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
+
+    /// Push a message onto the queue, returning `false` without pushing if the queue is full.
+    ///
+    /// Errors with [`NotEnoughMemory`][MMFError::NotEnoughMemory] if the message is larger than
+    /// [`slot_size`][Self::slot_size], and with [`MMF_NotFound`][MMFError::MMF_NotFound] if the MMF was closed.
+    pub fn push(&self, msg: &[u8]) -> MMFResult<bool> {
+        self.check()?;
+        if msg.len() > self.slot_size {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        let tail = self.counter(1);
+        let mut pos = guarded(|| tail.load(Ordering::Relaxed))?;
+        loop {
+            match self.load_sequence(pos)?.wrapping_sub(pos) as i32 {
+                0 => match guarded(|| {
+                    tail.compare_exchange_weak(pos, pos.wrapping_add(1), Ordering::Relaxed, Ordering::Relaxed)
+                })? {
+                    Ok(_) => break,
+                    Err(current) => pos = current,
+                },
+                // The slot still holds the message from the previous lap, the consumer hasn't caught up.
+                diff if diff < 0 => return Ok(false),
+                // Another producer claimed this position in the meantime.
+                _ => pos = guarded(|| tail.load(Ordering::Relaxed))?,
+            }
+        }
+        let slot = self.slot(pos);
+        // A message that fits in the slot fits in a `u32`.
+        let len = (msg.len() as u32).to_ne_bytes();
+        // Safety: claiming `pos` gives this producer the slot until it's published, and the message fits in it.
+        let copied = unsafe {
+            guarded_copy(len.as_ptr(), slot.add(size_of::<u32>()), len.len())
+                .and_then(|()| guarded_copy(msg.as_ptr(), slot.add(Self::SLOT_HEADER_SIZE), msg.len()))
+        };
+        // Publish even if copying failed, the consumer would get stuck on this slot otherwise.
+        let published = self.store_sequence(pos, pos.wrapping_add(1));
+        copied.and(published).map(|()| true)
+    }
+
+    /// Pop the oldest message into `buf`, returning `false` and leaving `buf` alone if the queue is empty.
+    ///
+    /// The buffer is cleared and grown as needed, the same as for [`read_into`][MemoryMappedFile::read_into]. Errors
+    /// with [`MMF_NotFound`][MMFError::MMF_NotFound] if the MMF was closed. Only one thread in one process may pop at
+    /// a time, see [`MpscMmf`].
+    pub fn pop(&self, buf: &mut Vec<u8>) -> MMFResult<bool> {
+        self.check()?;
+        let head = self.counter(0);
+        let pos = guarded(|| head.load(Ordering::Relaxed))?;
+        if self.load_sequence(pos)? != pos.wrapping_add(1) {
+            return Ok(false);
+        }
+        let slot = self.slot(pos);
+        let mut len = [0; size_of::<u32>()];
+        // Safety: the slot was published and is the consumer's until it's handed back.
+        let copied = unsafe { guarded_copy(slot.add(size_of::<u32>()), len.as_mut_ptr(), len.len()) }.and_then(|()| {
+            // Never trust a length that runs past the slot.
+            let len = (u32::from_ne_bytes(len) as usize).min(self.slot_size);
+            buf.clear();
+            buf.reserve_exact(len);
+            // Safety: the buffer was grown to fit `len` bytes, and they're all initialized once the copy succeeds.
+            unsafe {
+                guarded_copy(slot.add(Self::SLOT_HEADER_SIZE), buf.as_mut_ptr(), len)?;
+                buf.set_len(len);
+            }
+            Ok(())
+        });
+        // Hand the slot back for the next lap even if copying failed, so producers don't get stuck on it.
+        let handed_back = self
+            .store_sequence(pos, pos.wrapping_add(self.capacity as u32))
+            .and_then(|()| guarded(|| head.store(pos.wrapping_add(1), Ordering::Release)));
+        copied.and(handed_back).map(|()| true)
+    }
+
+    /// Error with [`MMF_NotFound`][MMFError::MMF_NotFound] if the MMF was closed.
+    fn check(&self) -> MMFResult<()> {
+        if self.mmf.closed.get() {
+            Err(MMFError::MMF_NotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get one of the counters at the start of the data, 0 for the head and 1 for the tail.
+    fn counter(&self, index: usize) -> &AtomicU32 {
+        // Safety: `mpsc` checked the data is aligned and has room for the header.
+        unsafe { AtomicU32::from_ptr(self.mmf.write_ptr.add(index * size_of::<u32>()).cast()) }
+    }
+
+    /// Get a pointer to the slot `pos` lands in.
+    fn slot(&self, pos: u32) -> *mut u8 {
+        let index = pos as usize & (self.capacity - 1);
+        // Safety: `mpsc` made sure all `capacity` slots fit in the data.
+        unsafe { self.mmf.write_ptr.add(Self::HEADER_SIZE + index * self.stride) }
+    }
+
+    /// Get the sequence of the slot `pos` lands in, which is stored relative to the slot's index.
+    fn load_sequence(&self, pos: u32) -> MMFResult<u32> {
+        let index = pos & (self.capacity as u32 - 1);
+        // Safety: slots are aligned, as `mpsc` rounded the stride up to keep them so.
+        let sequence = unsafe { AtomicU32::from_ptr(self.slot(pos).cast()) };
+        guarded(|| sequence.load(Ordering::Acquire)).map(|sequence| sequence.wrapping_add(index))
+    }
+
+    /// Set the sequence of the slot `pos` lands in, see [`load_sequence`][Self::load_sequence].
+    fn store_sequence(&self, pos: u32, value: u32) -> MMFResult<()> {
+        let index = pos & (self.capacity as u32 - 1);
+        // Safety: see `load_sequence`.
+        let sequence = unsafe { AtomicU32::from_ptr(self.slot(pos).cast()) };
+        guarded(|| sequence.store(value.wrapping_sub(index), Ordering::Release))
+    }
+}
+
+/// Implements a usable file-like interface for working with an MMF. Pass all input as bytes, please.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
+    /// Attempts to read bytes up to the entirety of the data as defined in [`Self::size`].
+    ///
+    /// This function succeeds if there is a value in [`Self::map_view`] but it cannot guarantee the data returned is
+    /// correct. This is an unfortunate side effect of having to work with raw pointers and bytes in memory.
+    /// Assuming nothing external has touched the memory region other than this class, it _should_ be valid data unless
+    /// it's marked as uninitialized. The returned error for this is an instance of the
+    /// [crate's error enum][crate::err::Error]
+    ///
+    /// - 1: Write Protected; the file has a write lock on it which means reading might return incomplete data, or the
+    ///   maximum amount of readers has been reached (this should not happen assuming all implementations are clean).
+    /// - 2: Invalid block; the lock is telling us this data has not yet been initialized.
+    /// - 5: File not found; the MMF isn't opened yet, no map view exists or the memory behind it faulted.
+    #[inline]
+    fn read(&self, count: usize) -> Result<Vec<u8>, MMFError> {
+        let mut buf = Vec::with_capacity(if count == 0 { self.size } else { count.min(self.size) });
+        self.read_to_buf(&mut buf, count)?;
+        Ok(buf)
+    }
+
+    /// Spinning form of [`read`][Self::read]
+    fn read_spin<F>(&self, count: usize, spinner: Option<F>) -> MMFResult<Vec<u8>>
+    where
+        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
+    {
+        let mut buf = Vec::with_capacity(if count == 0 { self.size } else { count.min(self.size) });
+        self.read_to_buf_spin(&mut buf, count, spinner)?;
+        Ok(buf)
+    }
+
+    /// See the documentation for [Self::read()], except this takes a buffer to write to.
+    ///
+    /// If the count is 0, the entire MMF will be read into the buffer. If the buffer is smaller than the amount of data
+    /// to be read, it _will be grown_ to fit the requested data, using [`Vec::reserve_exact`]. The returned error for
+    /// this is an instance of the [crate's error enum][crate::err::Error]
+    fn read_to_buf(&self, buffer: &mut Vec<u8>, count: usize) -> MMFResult<()> {
+        // Never claim more bytes than the MMF can actually copy into the buffer, they'd be uninitialized.
+        let to_read = if count == 0 { self.size } else { count.min(self.size) };
+
+        // `reserve_exact` reserves relative to the length, not the capacity. Make sure we can fit `to_read` bytes.
+        if buffer.capacity() < to_read {
+            buffer.reserve_exact(to_read - buffer.len());
+        }
+        unsafe {
+            self.read_to_raw(buffer.as_mut_ptr(), to_read)?;
+            buffer.set_len(to_read);
+        }
+        Ok(())
+    }
+
+    /// Spinning version of [`read_to_buf`][Self::read_to_buf]
+    fn read_to_buf_spin<F>(&self, buffer: &mut Vec<u8>, count: usize, spinner: Option<F>) -> MMFResult<()>
+    where
+        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
+    {
+        // Never claim more bytes than the MMF can actually copy into the buffer, they'd be uninitialized.
+        let to_read = if count == 0 { self.size } else { count.min(self.size) };
+
+        // `reserve_exact` reserves relative to the length, not the capacity. Make sure we can fit `to_read` bytes.
+        if buffer.capacity() < to_read {
+            buffer.reserve_exact(to_read - buffer.len());
+        }
+        unsafe {
+            self.read_to_raw_spin(buffer.as_mut_ptr(), to_read, spinner)?;
+            buffer.set_len(to_read);
+        }
+        Ok(())
+    }
+
+    /// Read into a raw pointer and pray it's valid for `count` bytes.
+    ///
+    /// If the count is 0, this operation will exit with a [non-specific error][MMFError::GeneralFailure]. This method
+    /// provides a best effort to ensure that the read portion of the copy is sound by clamping `count` to the MMFs
+    /// size. This prevents, at the very least, UB from reading beyond the end of the MMF. It also ensures the MMF is
+    /// opened and initialized, with the usual errors from [`read`][Self::read] to make these problems known to callers.
+    ///
+    /// # Safety
     /// It is the caller's responsibility to ensure that `buffer` is valid for at least `count` bytes. Failing to do so
     /// is UB. See the documentation for [`std::ptr::copy`] for safety concerns, the provided `buffer` is the `dst`.
-    unsafe fn read_to_raw(&self, buffer: *mut u8, count: usize) -> Result<(), MMFError> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, buffer), err))]
+    unsafe fn read_to_raw(&self, buffer: *mut u8, count: usize) -> MMFResult<usize> {
         if self.closed.get() {
             Err(MMFError::MMF_NotFound)
         } else if count == 0 {
@@ -437,16 +2317,17 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
             if !self.lock.initialized() {
                 return Err(MMFError::Uninitialized);
             }
-            self.lock.lock_read()?;
+            let guard = LockReleaseGuard::new(&self.lock);
+            guard.lock_read()?;
 
+            let count = count.min(self.size);
             // safety: memory may overlap with copy_to. With the size check, we also ensure we don't copy more bytes
             // than what fits in the buffer. If someone gave us a dirty slice, that's on them. Notably, they would
             // get UB from providing a slice with an incorrect internally registered length.
-            unsafe {
-                self.write_ptr.copy_to(buffer, count.min(self.size));
-            }
-            self.lock.unlock_read().unwrap();
-            Ok(())
+            let copied = unsafe { guarded_copy(self.write_ptr, buffer, count) };
+            guard.disarm();
+            let unlocked = self.lock.unlock_read();
+            copied.and(unlocked).map(|_| count)
         } else {
             Err(MMFError::MMF_NotFound)
         }
@@ -457,7 +2338,8 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
     /// # Safety
     /// It is the caller's responsibility to ensure that `buffer` is valid for at least `count` bytes. Failing to do so
     /// is UB. See the documentation for [`std::ptr::copy`] for safety concerns, the provided `buffer` is the `dst`.
-    unsafe fn read_to_raw_spin<F>(&self, buffer: *mut u8, count: usize, spinner: Option<F>) -> MMFResult<()>
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, buffer, spinner), err))]
+    unsafe fn read_to_raw_spin<F>(&self, buffer: *mut u8, count: usize, spinner: Option<F>) -> MMFResult<usize>
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
     {
@@ -466,20 +2348,21 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
         } else if count == 0 {
             Err(MMFError::GeneralFailure)
         } else if self.map_view.is_some() {
+            let guard = LockReleaseGuard::new(&self.lock);
             if let Some(mut spinner) = spinner {
-                spinner(&self.lock, usize::MAX)?;
+                spinner(&guard, usize::MAX)?;
             } else {
-                LOCK::spin_and_lock_read(&self.lock, usize::MAX)?;
+                LockReleaseGuard::spin_and_lock_read(&guard, usize::MAX)?;
             }
 
+            let count = count.min(self.size);
             // safety: memory may be overlapped with copy_to. With the size check, we also ensure we don't copy more
             // bytes than what fits in the buffer. If someone gave us a dirty slice, that's on them.
             // Notably, they would get UB from providing a pointer with too little space.
-            unsafe {
-                self.write_ptr.copy_to(buffer, count.min(self.size));
-            }
-            self.lock.unlock_read().unwrap();
-            Ok(())
+            let copied = unsafe { guarded_copy(self.write_ptr, buffer, count) };
+            guard.disarm();
+            let unlocked = self.lock.unlock_read();
+            copied.and(unlocked).map(|_| count)
         } else {
             Err(MMFError::MMF_NotFound)
         }
@@ -493,10 +2376,24 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
     /// bytes to signal and flag locking.The returned error for this is an instance of the
     /// [crate's error enum][crate::err::Error]
     ///
+    /// Every check happens before the write lock is taken, and the buffer is copied in one go while holding it, so
+    /// readers taking the lock never see part of a write that's still in progress. The one way a write can fail
+    /// halfway is the copy faulting on memory that isn't committed, as can happen for MMFs made with
+    /// [`new_reserved`][MemoryMappedFile::new_reserved]. That errors with [`MMF_NotFound`][MMFError::MMF_NotFound]
+    /// and leaves whatever was copied before the fault in place, so the write must be retried once the pages are
+    /// committed. For MMFs guarded by a [`SeqLock`], the sequence is bumped around the copy, so
+    /// [`read_consistent`][MemoryMappedFile::read_consistent] and [`read_seqlock`][MemoryMappedFile::read_seqlock]
+    /// retry rather than hand out a torn read.
+    ///
     /// Error codes produced by this function:
     /// - 0 or 1: Access denied; the lock could not be acquired or the MMF is read-only.
     /// - 4: Not enough memory; the write was blocked because it was too large.
+    /// - 5: MMF not found; the MMF was closed, or the copy faulted and may have been partially written.
     /// - All errors from [Self::read()] as a read is required to update the lock.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, buffer), fields(len = buffer.len()), err)
+    )]
     fn write(&self, buffer: &[u8]) -> MMFResult<()> {
         if self.readonly || self.closed.get() {
             return Err(MMFError::MMF_NotFound);
@@ -507,17 +2404,24 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
         } else if !self.lock.initialized() {
             Err(MMFError::Uninitialized)
         } else if self.map_view.is_some() {
-            self.lock.lock_write()?;
+            let guard = LockReleaseGuard::new(&self.lock);
+            guard.lock_write()?;
             let src_ptr = buffer.as_ptr();
             // We ensured this size is correct and filled out when instantiating the MMF, this is just writing the same
             // amount of bytes to the same place in memory.
-            unsafe { src_ptr.copy_to(self.write_ptr, cap) };
-            self.lock.unlock_write()
+            let copied = unsafe { guarded_copy(src_ptr, self.write_ptr, cap) };
+            guard.disarm();
+            let unlocked = self.lock.unlock_write();
+            copied.and(unlocked)
         } else {
             Err(MMFError::MMF_NotFound)
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, buffer, spinner), fields(len = buffer.len()), err)
+    )]
     fn write_spin<F>(&self, buffer: &[u8], spinner: Option<F>) -> MMFResult<()>
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
@@ -529,16 +2433,19 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
         if cap < buffer.len() {
             Err(MMFError::NotEnoughMemory)
         } else if self.map_view.is_some() {
+            let guard = LockReleaseGuard::new(&self.lock);
             if let Some(mut spinner) = spinner {
-                spinner(&self.lock, usize::MAX)?;
+                spinner(&guard, usize::MAX)?;
             } else {
-                LOCK::spin_and_lock_write(&self.lock, usize::MAX)?;
+                LockReleaseGuard::spin_and_lock_write(&guard, usize::MAX)?;
             }
             let src_ptr = buffer.as_ptr();
             // We ensured this size is correct and filled out when instantiating the MMF, this is just writing the same
             // amount of bytes to the same place in memory.
-            unsafe { src_ptr.copy_to(self.write_ptr, cap) };
-            self.lock.unlock_write()
+            let copied = unsafe { guarded_copy(src_ptr, self.write_ptr, cap) };
+            guard.disarm();
+            let unlocked = self.lock.unlock_write();
+            copied.and(unlocked)
         } else {
             Err(MMFError::MMF_NotFound)
         }
@@ -553,6 +2460,341 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
     }
 }
 
+/// Releases the locks a read or write took if the thread unwinds before it could release them itself.
+///
+/// Create one right before taking the lock, take the lock through it and [`disarm`][Self::disarm] it once the data was
+/// copied. Spinners are handed the guard rather than the lock itself, so whatever they take is counted too. If the
+/// guard gets dropped while panicking instead, e.g. because a spinner panicked while holding the lock, exactly what was
+/// taken through it is released again. Only this guard's own count is used, so other threads using the same MMF at the
+/// same time never have their locks released, nor keep this guard from releasing its own. Without the `unwind_guard`
+/// feature, this does nothing.
+#[cfg(feature = "impl_mmf")]
+struct LockReleaseGuard<'a, LOCK: MMFLock> {
+    /// The lock to take and release.
+    lock: &'a LOCK,
+    /// Whether the write lock was taken through this guard and not released yet.
+    write: Cell<bool>,
+    /// The amount of read locks taken through this guard and not released yet.
+    reads: Cell<u32>,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<'a, LOCK: MMFLock> LockReleaseGuard<'a, LOCK> {
+    /// Wrap the lock, holding nothing yet.
+    fn new(lock: &'a LOCK) -> Self {
+        Self { lock, write: Cell::new(false), reads: Cell::new(0) }
+    }
+
+    /// The operation finished without unwinding, leave the lock alone.
+    fn disarm(self) {
+        std::mem::forget(self)
+    }
+
+    /// Count a read lock taken through this guard, if taking it worked out.
+    fn took_read(&self, res: MMFResult<()>) -> MMFResult<()> {
+        if res.is_ok() {
+            self.reads.set(self.reads.get() + 1);
+        }
+        res
+    }
+
+    /// Note the write lock was taken through this guard, if taking it worked out.
+    fn took_write(&self, res: MMFResult<()>) -> MMFResult<()> {
+        if res.is_ok() {
+            self.write.set(true);
+        }
+        res
+    }
+}
+
+/// Forwards to the wrapped lock, keeping count of what is taken and released through the guard.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> MMFLock for LockReleaseGuard<'_, LOCK> {
+    /// Forwarded to the wrapped lock, counting the read lock on success.
+    fn lock_read(&self) -> MMFResult<()> {
+        self.took_read(self.lock.lock_read())
+    }
+
+    /// Forwarded to the wrapped lock, uncounting the read lock on success.
+    fn unlock_read(&self) -> MMFResult<()> {
+        let res = self.lock.unlock_read();
+        if res.is_ok() {
+            self.reads.set(self.reads.get().saturating_sub(1));
+        }
+        res
+    }
+
+    /// Forwarded to the wrapped lock, noting the write lock on success.
+    fn lock_write(&self) -> MMFResult<()> {
+        self.took_write(self.lock.lock_write())
+    }
+
+    /// Forwarded to the wrapped lock, forgetting the write lock on success.
+    fn unlock_write(&self) -> MMFResult<()> {
+        let res = self.lock.unlock_write();
+        if res.is_ok() {
+            self.write.set(false);
+        }
+        res
+    }
+
+    /// Forwarded to the wrapped lock
+    fn initialized(&self) -> bool {
+        self.lock.initialized()
+    }
+
+    /// Forwarded to the wrapped lock, counting the read lock on success.
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        lock.took_read(LOCK::spin_and_lock_read(lock.lock, max_tries))
+    }
+
+    /// Forwarded to the wrapped lock, noting the write lock on success.
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        lock.took_write(LOCK::spin_and_lock_write(lock.lock, max_tries))
+    }
+
+    /// Guards only ever wrap a lock that already exists.
+    unsafe fn from_existing(_pointer: *mut u8) -> Self {
+        panic!("Lock guards can't be built from a pointer!")
+    }
+
+    /// Guards only ever wrap a lock that already exists.
+    unsafe fn from_raw(_pointer: *mut u8) -> Self {
+        panic!("Lock guards can't be built from a pointer!")
+    }
+
+    /// The read locks taken through this guard and not released yet.
+    fn reader_guard_count(&self) -> u32 {
+        self.reads.get()
+    }
+
+    /// Whether the write lock was taken through this guard and not released yet.
+    fn holds_write_lock(&self) -> bool {
+        self.write.get()
+    }
+
+    /// Forwarded to the wrapped lock
+    fn write_locked(&self) -> bool {
+        self.lock.write_locked()
+    }
+
+    /// Forwarded to the wrapped lock
+    fn reader_count(&self) -> u32 {
+        self.lock.reader_count()
+    }
+
+    /// Forwarded to the wrapped lock
+    fn set_init(&self) {
+        self.lock.set_init()
+    }
+
+    /// Thin wrapper around [`Self::set_init`] that returns self for chaining calls.
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+}
+
+/// Release what was taken through the guard, but only when unwinding. Errors just return early.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Drop for LockReleaseGuard<'_, LOCK> {
+    /// Unlock whatever this guard is responsible for, ignoring errors as there's nobody left to report them to.
+    fn drop(&mut self) {
+        if !cfg!(feature = "unwind_guard") || !std::thread::panicking() {
+            return;
+        }
+        if self.write.get() {
+            _ = self.lock.unlock_write();
+        }
+        for _ in 0..self.reads.get() {
+            if self.lock.unlock_read().is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// A borrowed part of a [`MemoryMappedFile`], acting as an MMF of its own.
+///
+/// All reads and writes are offset into the data portion of the parent, and are limited to the size of this part.
+/// Locking is done through the parent's lock, so this is subject to the same locking as the rest of the MMF.
+/// Create these through [`MemoryMappedFile::subview`].
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct SubMmf<'a, LOCK: MMFLock> {
+    /// The MMF this is a part of.
+    parent: &'a MemoryMappedFile<LOCK>,
+    /// Offset into the parent's data portion.
+    offset: usize,
+    /// The size of this part.
+    size: usize,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> SubMmf<'_, LOCK> {
+    /// Get the offset of this part into the parent's data portion.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The pointer to the start of this part of the data.
+    fn data_ptr(&self) -> *mut u8 {
+        // safety: the offset was checked to be within the parent's data portion on creation.
+        unsafe { self.parent.write_ptr.add(self.offset) }
+    }
+}
+
+/// Implements the same file-like interface as [`MemoryMappedFile`] does, offset into the parent.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Mmf for SubMmf<'_, LOCK> {
+    /// See [`MemoryMappedFile::read`], limited to this part of the data.
+    fn read(&self, count: usize) -> MMFResult<Vec<u8>> {
+        let mut buf = Vec::with_capacity(if count == 0 { self.size } else { count.min(self.size) });
+        self.read_to_buf(&mut buf, count)?;
+        Ok(buf)
+    }
+
+    /// Spinning form of [`read`][Self::read]
+    fn read_spin<F>(&self, count: usize, spinner: Option<F>) -> MMFResult<Vec<u8>>
+    where
+        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
+    {
+        let mut buf = Vec::with_capacity(if count == 0 { self.size } else { count.min(self.size) });
+        self.read_to_buf_spin(&mut buf, count, spinner)?;
+        Ok(buf)
+    }
+
+    /// See [`MemoryMappedFile::read_to_buf`], limited to this part of the data.
+    fn read_to_buf(&self, buffer: &mut Vec<u8>, count: usize) -> MMFResult<()> {
+        let to_read = if count == 0 { self.size } else { count.min(self.size) };
+
+        if buffer.capacity() < to_read {
+            buffer.reserve_exact(to_read - buffer.len());
+        }
+        unsafe {
+            self.read_to_raw(buffer.as_mut_ptr(), to_read)?;
+            buffer.set_len(to_read);
+        }
+        Ok(())
+    }
+
+    /// Spinning version of [`read_to_buf`][Self::read_to_buf]
+    fn read_to_buf_spin<F>(&self, buffer: &mut Vec<u8>, count: usize, spinner: Option<F>) -> MMFResult<()>
+    where
+        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
+    {
+        let to_read = if count == 0 { self.size } else { count.min(self.size) };
+
+        if buffer.capacity() < to_read {
+            buffer.reserve_exact(to_read - buffer.len());
+        }
+        unsafe {
+            self.read_to_raw_spin(buffer.as_mut_ptr(), to_read, spinner)?;
+            buffer.set_len(to_read);
+        }
+        Ok(())
+    }
+
+    /// See [`MemoryMappedFile::read_to_raw`], limited to this part of the data.
+    ///
+    /// # Safety
+    /// It is the caller's responsibility to ensure that `buffer` is valid for at least `count` bytes.
+    unsafe fn read_to_raw(&self, buffer: *mut u8, count: usize) -> MMFResult<usize> {
+        if self.parent.closed.get() || self.parent.map_view.is_none() {
+            Err(MMFError::MMF_NotFound)
+        } else if count == 0 {
+            Err(MMFError::GeneralFailure)
+        } else if !self.parent.lock.initialized() {
+            Err(MMFError::Uninitialized)
+        } else {
+            let guard = LockReleaseGuard::new(&self.parent.lock);
+            guard.lock_read()?;
+            let count = count.min(self.size);
+            // safety: clamped to the size of this part, which was checked to fit in the parent on creation.
+            let copied = unsafe { guarded_copy(self.data_ptr(), buffer, count) };
+            guard.disarm();
+            let unlocked = self.parent.lock.unlock_read();
+            copied.and(unlocked).map(|_| count)
+        }
+    }
+
+    /// Spinning version of [`read_to_raw`][Self::read_to_raw]
+    ///
+    /// # Safety
+    /// It is the caller's responsibility to ensure that `buffer` is valid for at least `count` bytes.
+    unsafe fn read_to_raw_spin<F>(&self, buffer: *mut u8, count: usize, spinner: Option<F>) -> MMFResult<usize>
+    where
+        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
+    {
+        if self.parent.closed.get() || self.parent.map_view.is_none() {
+            Err(MMFError::MMF_NotFound)
+        } else if count == 0 {
+            Err(MMFError::GeneralFailure)
+        } else {
+            let guard = LockReleaseGuard::new(&self.parent.lock);
+            if let Some(mut spinner) = spinner {
+                spinner(&guard, usize::MAX)?;
+            } else {
+                LockReleaseGuard::spin_and_lock_read(&guard, usize::MAX)?;
+            }
+            let count = count.min(self.size);
+            // safety: clamped to the size of this part, which was checked to fit in the parent on creation.
+            let copied = unsafe { guarded_copy(self.data_ptr(), buffer, count) };
+            guard.disarm();
+            let unlocked = self.parent.lock.unlock_read();
+            copied.and(unlocked).map(|_| count)
+        }
+    }
+
+    /// See [`MemoryMappedFile::write`], limited to this part of the data.
+    fn write(&self, buffer: &[u8]) -> MMFResult<()> {
+        if self.parent.readonly || self.parent.closed.get() || self.parent.map_view.is_none() {
+            Err(MMFError::MMF_NotFound)
+        } else if buffer.len() > self.size {
+            Err(MMFError::NotEnoughMemory)
+        } else if !self.parent.lock.initialized() {
+            Err(MMFError::Uninitialized)
+        } else {
+            let guard = LockReleaseGuard::new(&self.parent.lock);
+            guard.lock_write()?;
+            // safety: the buffer fits in this part, which was checked to fit in the parent on creation.
+            let copied = unsafe { guarded_copy(buffer.as_ptr(), self.data_ptr(), buffer.len()) };
+            guard.disarm();
+            let unlocked = self.parent.lock.unlock_write();
+            copied.and(unlocked)
+        }
+    }
+
+    /// Spinning version of [`write`][Self::write]
+    fn write_spin<F>(&self, buffer: &[u8], spinner: Option<F>) -> MMFResult<()>
+    where
+        F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
+    {
+        if self.parent.readonly || self.parent.closed.get() || self.parent.map_view.is_none() {
+            Err(MMFError::MMF_NotFound)
+        } else if buffer.len() > self.size {
+            Err(MMFError::NotEnoughMemory)
+        } else {
+            let guard = LockReleaseGuard::new(&self.parent.lock);
+            if let Some(mut spinner) = spinner {
+                spinner(&guard, usize::MAX)?;
+            } else {
+                LockReleaseGuard::spin_and_lock_write(&guard, usize::MAX)?;
+            }
+            // safety: the buffer fits in this part, which was checked to fit in the parent on creation.
+            let copied = unsafe { guarded_copy(buffer.as_ptr(), self.data_ptr(), buffer.len()) };
+            guard.disarm();
+            let unlocked = self.parent.lock.unlock_write();
+            copied.and(unlocked)
+        }
+    }
+
+    /// Returns the size of this part of the MMF.
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
 /// Small struct wrapping a Windows type just to spare my eyes.
 #[derive(Debug, Clone)]
 pub struct MemoryMappedView {
@@ -572,7 +2814,7 @@ impl From<MEMORY_MAPPED_VIEW_ADDRESS> for MemoryMappedView {
 impl MemoryMappedView {
     /// Unmaps the view to release resources.
     ///
-    /// There is currently no way to undo this, short of closing the MMF.
+    /// The only way to undo this is to [`remap`][Self::remap] the view, which requires the handle to the MMF.
     /// If you need to do or change something that causes unmapping of the view, and you do need to keep the relevant
     /// data, it's best to open a new MMF before closing it. When the last handle to an MMF closes, it's destroyed.
     fn unmap(&self) -> MMFResult<()> {
@@ -586,6 +2828,79 @@ impl MemoryMappedView {
     }
 }
 
+/// Query the OS for info on the view.
+#[cfg(feature = "impl_mmf")]
+impl MemoryMappedView {
+    /// Get the size of the region of pages backing this view, including the lock bytes.
+    ///
+    /// The OS rounds this up to a multiple of the page size, so it may be larger than what was requested on creation.
+    fn region_size(&self) -> MMFResult<usize> {
+        self.query().map(|info| info.RegionSize)
+    }
+
+    /// Check whether the pages backing this view can be written to.
+    fn writable(&self) -> MMFResult<bool> {
+        let writable = PAGE_READWRITE | PAGE_WRITECOPY | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY;
+        self.query().map(|info| info.Protect.0 & writable.0 != 0)
+    }
+
+    /// Check whether the pages backing this view are committed, meaning they're actually backed by memory.
+    fn committed(&self) -> MMFResult<bool> {
+        self.query().map(|info| info.State == MEM_COMMIT)
+    }
+
+    /// Ask the OS for info on the region of pages backing this view.
+    fn query(&self) -> MMFResult<MEMORY_BASIC_INFORMATION> {
+        query_pages(self.address.Value.cast_const())
+    }
+}
+
+/// Map (parts of) an MMF into a view and move it around.
+#[cfg(feature = "impl_mmf")]
+impl MemoryMappedView {
+    /// Map `len` bytes of the MMF behind `handle` into a new view, starting at `offset` into the mapping.
+    ///
+    /// The offset must be a multiple of [the system allocation granularity][system_allocation_granularity], or this
+    /// errors with [`UnalignedOffset`][MMFError::UnalignedOffset]. Use [`align_offset`] to round it down first.
+    pub fn map(handle: HANDLE, offset: u64, len: usize) -> MMFResult<Self> {
+        Self::map_with_access(handle, offset, len, FILE_MAP_ALL_ACCESS)
+    }
+
+    /// Same as [`map`][Self::map], except the view is mapped with `access` rather than full access.
+    ///
+    /// The handle must have been opened with at least the access requested here, or the OS refuses to map the view.
+    pub fn map_with_access(handle: HANDLE, offset: u64, len: usize, access: FILE_MAP) -> MMFResult<Self> {
+        if offset % u64::from(system_allocation_granularity()) != 0 {
+            return Err(MMFError::UnalignedOffset);
+        }
+        let (off_low, off_high) = offset.split();
+        // Safety: `MapViewOfFile` returns a NULL pointer on failure, which is checked right after.
+        let address = try_seh(|| unsafe { MapViewOfFile(handle, access, off_high, off_low, len) })?;
+        if address.Value.is_null() {
+            Err(WErr::from_win32().into())
+        } else {
+            Ok(address.into())
+        }
+    }
+
+    /// Point this view at a different range of the MMF behind `handle`.
+    ///
+    /// This lets you keep a window into a mapping that's bigger than you want resident. The new range is mapped before
+    /// the current one is unmapped, so the view is left untouched if mapping fails. Any pointers into the old range are
+    /// invalid afterwards.
+    pub fn remap(&mut self, handle: HANDLE, offset: u64, len: usize) -> MMFResult<()> {
+        let new = Self::map(handle, offset, len)?;
+        // The old view gets unmapped when it's dropped here.
+        drop(std::mem::replace(self, new));
+        Ok(())
+    }
+
+    /// Get a pointer to the start of the view.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.address.Value.cast()
+    }
+}
+
 /// Handle unmapping on drop.
 impl Drop for MemoryMappedView {
     /// Unmap the view before dropping.
@@ -594,12 +2909,37 @@ impl Drop for MemoryMappedView {
     }
 }
 
+/// Show the metadata of the MMF, without ever touching the data behind it.
+impl<LOCK: MMFLock> std::fmt::Debug for MemoryMappedFile<LOCK> {
+    /// Formats the name, size, state flags and the decoded lock state. Pointers are left out on purpose, they say
+    /// nothing about the MMF and the data isn't part of this struct either. The lock is only read while it's mapped.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mapped = self.map_view.is_some();
+        f.debug_struct("MemoryMappedFile")
+            .field("name", &self.name.as_str())
+            .field("size", &self.size)
+            .field("readonly", &self.readonly)
+            .field("closed", &self.closed.get())
+            .field("mapped", &mapped)
+            .field("initialized", &(mapped && self.lock.initialized()))
+            .field("write_locked", &(mapped && self.lock.write_locked()))
+            .field("readers", &if mapped { self.lock.reader_count() } else { 0 })
+            .finish_non_exhaustive()
+    }
+}
+
 /// Implement closing the handle to the MMF before dropping it, so the system can clean up resources.
 #[cfg(feature = "impl_mmf")]
 impl<LOCK: MMFLock> Drop for MemoryMappedFile<LOCK> {
-    /// Ignore any errors when closing the handle.
+    /// Ignore any errors when closing the handle. Handles that were explicitly closed before are left alone.
+    ///
+    /// Locks this instance still holds are released first, so a leaked write lock doesn't deadlock every other process
+    /// and leaked read locks don't count against every other reader forever.
     fn drop(&mut self) {
-        self.close().unwrap_or(())
+        self.release_held_locks();
+        if !self.closed.get() {
+            self.close().unwrap_or(())
+        }
     }
 }
 
@@ -19,6 +19,15 @@
 //!
 //! The errors in this crate still need some work. They're in active development.
 //!
+//! ## Initialization
+//! Every lock in this module keeps its initialization state in the most significant byte of its four bytes, and they
+//! all read it the same way: the lock is uninitialized if and only if every bit of that byte is set, see
+//! [`INITIALIZE_MASK`]. Locks are created in that state by `from_raw`, and `set_init` clears the byte. Anything else
+//! counts as initialized, including a byte that's only partially set, as locks are free to keep state of their own in
+//! those bits. [`RWLock`] uses the top bit for its write lock, for instance. Locks that share this convention agree
+//! about each other's initialization state, so an MMF initialized through one is never mistaken for a fresh one by
+//! another.
+//!
 //! Assuming you're comfortable waiting on the locks to be claimable, [`MMFLock::spin_and_lock_read`] and
 //! [`MMFLock::spin_and_lock_write`] will be your friends, as you'd only need to handle the case where you spin more
 //! than what your native pointer size holds, and you should be seeing problems long before then.
@@ -26,10 +35,24 @@
 //! No guarantees are made about the usefulness and safety of this code, and the project maintainer is not liable for
 //! any damages, be they to your PC or your (mental) health.
 
-use std::sync::atomic::{fence, AtomicU32, Ordering};
+#[cfg(all(test, feature = "impl_lock"))]
+use std::sync::Arc;
+use std::{
+    hint::spin_loop,
+    sync::atomic::{fence, AtomicBool, AtomicU32, Ordering},
+};
 
 use super::err::{Error, MMFResult};
 
+/// Mask for the byte holding the initialization state, which is uninitialized when all of its bits are set.
+pub const INITIALIZE_MASK: u32 = 255 << 24;
+
+/// Check the initialization state of any lock in this module, see [the module docs][self] for the convention.
+#[cfg(feature = "impl_lock")]
+fn initialized(chunk: u32) -> bool {
+    (chunk & INITIALIZE_MASK) < INITIALIZE_MASK
+}
+
 /// Blanket trait for implementing locks to be used with MMFs.
 ///
 /// The default implementation applied to [`RWLock`] can be used with a custom MMF implementation,
@@ -40,20 +63,27 @@ use super::err::{Error, MMFResult};
 /// Users are free to use the default lock and MMF implementations independently of one another.
 pub trait MMFLock {
     /// Acquire a readlock, if at all possible. Otherwise error.
+    #[must_use = "lock errors mean the lock is not in the state you think it is"]
     fn lock_read(&self) -> MMFResult<()>;
     /// Release a readlock, clearing the readlock state if this was the last lock.
+    #[must_use = "lock errors mean the lock is not in the state you think it is"]
     fn unlock_read(&self) -> MMFResult<()>;
     /// Lock this file for writing if possible.
+    #[must_use = "lock errors mean the lock is not in the state you think it is"]
     fn lock_write(&self) -> MMFResult<()>;
     /// Nuke all existing write locks as there can only be one, legally.
+    #[must_use = "lock errors mean the lock is not in the state you think it is"]
     fn unlock_write(&self) -> MMFResult<()>;
     /// Check if the lock is initialized
+    #[must_use]
     fn initialized(&self) -> bool;
     /// Spin until the lock can be taken, then take it.
+    #[must_use = "lock errors mean the lock is not in the state you think it is"]
     fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()>
     where
         Self: Sized;
     /// Spin until the lock can be taken, then take it.
+    #[must_use = "lock errors mean the lock is not in the state you think it is"]
     fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()>
     where
         Self: Sized;
@@ -71,14 +101,113 @@ pub trait MMFLock {
     unsafe fn from_raw(pointer: *mut u8) -> Self
     where
         Self: Sized;
+    /// Non-panicking version of [`from_existing`][MMFLock::from_existing], erroring on a null pointer instead.
+    ///
+    /// # Safety
+    /// The same bounds apply as for `from_existing`, except `pointer` is allowed to be null.
+    unsafe fn try_from_existing(pointer: *mut u8) -> MMFResult<Self>
+    where
+        Self: Sized,
+    {
+        if pointer.is_null() {
+            Err(Error::NullPointer)
+        } else {
+            Ok(Self::from_existing(pointer))
+        }
+    }
+    /// Non-panicking version of [`from_raw`][MMFLock::from_raw], erroring on a null pointer instead.
+    ///
+    /// # Safety
+    /// The same bounds apply as for `from_raw`, except `pointer` is allowed to be null.
+    unsafe fn try_from_raw(pointer: *mut u8) -> MMFResult<Self>
+    where
+        Self: Sized,
+    {
+        if pointer.is_null() {
+            Err(Error::NullPointer)
+        } else {
+            Ok(Self::from_raw(pointer))
+        }
+    }
+    /// The amount of read locks taken through this instance that haven't been released yet.
+    ///
+    /// Owners of the lock use this to release whatever is still held when they're dropped, so the shared reader count
+    /// doesn't leak. Locks that don't hold anything while reading, or don't keep track, report 0.
+    fn reader_guard_count(&self) -> u32 {
+        0
+    }
+    /// Whether this instance took the write lock and hasn't released it yet.
+    ///
+    /// Like [`reader_guard_count`][MMFLock::reader_guard_count], this lets owners release the lock when dropped. Locks
+    /// that don't keep track report `false`.
+    fn holds_write_lock(&self) -> bool {
+        false
+    }
+    /// Whether anyone holds the write lock right now, through any instance in any process.
+    ///
+    /// This is a snapshot for diagnostics like [`Debug`] output, as the lock may change right after it's taken. Locks
+    /// that can't tell report `false`, as do uninitialized ones.
+    fn write_locked(&self) -> bool {
+        false
+    }
+    /// The amount of read locks held right now, through any instance in any process.
+    ///
+    /// Like [`write_locked`][MMFLock::write_locked], this is only a snapshot. Locks that don't count their readers
+    /// report 0, as do uninitialized ones.
+    fn reader_count(&self) -> u32 {
+        0
+    }
     /// Set the lock's first byte to an initialized state.
     fn set_init(&self);
     /// Self-consuming wrapper to chain initialization with [`set_init`][`MMFLock::set_init`]
+    #[must_use = "this consumes the lock and hands it back, use the returned lock"]
     fn initialize(self) -> Self
     where
         Self: Sized;
 }
 
+/// The memory orderings an [`RWLock`] uses for the atomic operations in taking and releasing it.
+///
+/// Locks are [`Strict`][Self::Strict] unless told otherwise through [`RWLock::with_ordering`].
+#[cfg(feature = "impl_lock")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockOrdering {
+    /// Acquire on loads, AcqRel on successful swaps and a full fence after each operation. Data written under the lock
+    /// is visible to whoever takes it next, in this process or any other.
+    #[default]
+    Strict,
+    /// Relaxed everything and no fences. The lock state itself stays consistent, but taking it no longer orders
+    /// anything written to the data, so the lock stops guarding the data. See [`RWLock::with_ordering`] for what that
+    /// asks of the caller.
+    Relaxed,
+}
+
+#[cfg(feature = "impl_lock")]
+impl LockOrdering {
+    /// The ordering for loading the lock state, and for failed swaps.
+    fn load(self) -> Ordering {
+        match self {
+            Self::Strict => Ordering::Acquire,
+            Self::Relaxed => Ordering::Relaxed,
+        }
+    }
+
+    /// The ordering for successfully swapping the lock state.
+    fn swap(self) -> Ordering {
+        match self {
+            Self::Strict => Ordering::AcqRel,
+            Self::Relaxed => Ordering::Relaxed,
+        }
+    }
+
+    /// The fence after taking or releasing the lock, if any.
+    fn fence(self) {
+        if self == Self::Strict {
+            fence(Ordering::SeqCst);
+        }
+    }
+}
+
 /// Packed binary data to represent the locking state of the MMF.
 ///
 /// The wrapper implementation must set these bytes depending on the situation and actions being taken.
@@ -99,12 +228,18 @@ pub struct RWLock<'a> {
     /// An Atomic reference to the first 4 bytes in the MemoryMappedView.
     /// Alignment is not an issue considering Windows aligns views to pointers by default.
     chunk: &'a AtomicU32,
+    /// The amount of read locks taken through this instance, to release them if the owner goes away.
+    held_reads: AtomicU32,
+    /// Whether the write lock was taken through this instance, to release it if the owner goes away.
+    held_write: AtomicBool,
+    /// The memory orderings used when taking and releasing the lock.
+    ordering: LockOrdering,
 }
 
 #[cfg(feature = "impl_lock")]
 impl RWLock<'_> {
     /// Mask to check if the lock is initialized
-    pub const INITIALIZE_MASK: u32 = 255 << 24;
+    pub const INITIALIZE_MASK: u32 = INITIALIZE_MASK;
     /// Mask to check if it's locked for WRITING
     pub const WRITE_LOCK_MASK: u32 = 0b1 << 31;
     /// Mask to check if it's locked for READING
@@ -115,7 +250,38 @@ impl RWLock<'_> {
     /// Regardless of locking state, and abuse of the 7 empty bits, a lock _should_ not have all bits on the first byte
     /// set to one. If it does, either the lock isn't initialized, or the user is not being very smart.
     fn initialized(chunk: u32) -> bool {
-        (chunk & Self::INITIALIZE_MASK) < Self::INITIALIZE_MASK
+        initialized(chunk)
+    }
+
+    /// Check whether the lock state behind `pointer` looks initialized, without building a lock.
+    ///
+    /// This helps decide between [`from_existing`][MMFLock::from_existing] to attach to a lock someone already set up,
+    /// and [`from_raw`][MMFLock::from_raw] to start fresh. A null pointer is never initialized.
+    ///
+    /// # Safety
+    /// The pointer must be null, or valid and aligned for reading the four bytes of lock state.
+    pub unsafe fn is_initialized_at(pointer: *const u8) -> bool {
+        !pointer.is_null() && Self::initialized(AtomicU32::from_ptr(pointer.cast_mut().cast()).load(Ordering::Acquire))
+    }
+
+    /// Use `ordering` for taking and releasing this lock from here on.
+    ///
+    /// See [`LockOrdering`] for what the orderings mean.
+    ///
+    /// # Safety
+    /// Switching to [`Strict`][LockOrdering::Strict] is always fine. With [`Relaxed`][LockOrdering::Relaxed], reads
+    /// and writes done under this lock are no longer ordered with each other, so reading and writing the data at the
+    /// same time through the lock, from this process or any other, is a data race and thus UB. The caller must make
+    /// sure nothing reads the data while it may be written, or synchronize access to it some other way.
+    #[must_use = "this hands back the reconfigured lock"]
+    pub unsafe fn with_ordering(mut self, ordering: LockOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Get the memory orderings used for taking and releasing this lock.
+    pub fn ordering(&self) -> LockOrdering {
+        self.ordering
     }
 
     /// Check if the lock is held for reading. This should only prevent new write locks.
@@ -177,7 +343,12 @@ impl MMFLock for RWLock<'_> {
         if pointer.is_null() {
             panic!("Never, ever pass a null pointer into a lock!")
         }
-        Self { chunk: AtomicU32::from_ptr(pointer.cast()) }
+        Self {
+            chunk: AtomicU32::from_ptr(pointer.cast()),
+            held_reads: AtomicU32::new(0),
+            held_write: AtomicBool::new(false),
+            ordering: LockOrdering::Strict,
+        }
     }
 
     /// Similar to [`Self::from_existing`], except it clears all state and ensures [`Self::initialized`] returns false.
@@ -189,7 +360,12 @@ impl MMFLock for RWLock<'_> {
         if pointer.is_null() {
             panic!("Never, ever pass a null pointer into a lock!")
         }
-        let lock = Self { chunk: AtomicU32::from_ptr(pointer.cast()) };
+        let lock = Self {
+            chunk: AtomicU32::from_ptr(pointer.cast()),
+            held_reads: AtomicU32::new(0),
+            held_write: AtomicBool::new(false),
+            ordering: LockOrdering::Strict,
+        };
         lock.chunk.store(Self::INITIALIZE_MASK, Ordering::Release);
         lock
     }
@@ -223,10 +399,43 @@ impl MMFLock for RWLock<'_> {
         Self::initialized(self.chunk.load(Ordering::Acquire))
     }
 
+    /// Read locks taken through this instance and not released yet.
+    fn reader_guard_count(&self) -> u32 {
+        self.held_reads.load(Ordering::Relaxed)
+    }
+
+    /// Whether the write lock was taken through this instance and not released yet.
+    fn holds_write_lock(&self) -> bool {
+        self.held_write.load(Ordering::Relaxed)
+    }
+
+    /// Decode the write bit from the current lock state.
+    fn write_locked(&self) -> bool {
+        let chunk = self.chunk.load(Ordering::Acquire);
+        Self::initialized(chunk) && Self::writelocked(chunk)
+    }
+
+    /// Decode the reader count from the current lock state.
+    fn reader_count(&self) -> u32 {
+        let chunk = self.chunk.load(Ordering::Acquire);
+        if Self::initialized(chunk) {
+            chunk & Self::READ_LOCK_MASK
+        } else {
+            0
+        }
+    }
+
     /// Increment the counter for read locks ***if and only if*** we can safely lock this for reading
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(iterations), ret, err)
+    )]
     fn lock_read(&self) -> MMFResult<()> {
+        let mut iterations = 0_usize;
         loop {
-            let chunk = self.chunk.load(Ordering::Acquire);
+            iterations += 1;
+            trace_iterations(iterations);
+            let chunk = self.chunk.load(self.ordering.load());
 
             if !Self::initialized(chunk) {
                 return Err(Error::Uninitialized);
@@ -240,52 +449,76 @@ impl MMFLock for RWLock<'_> {
                 return Err(Error::MaxReaders);
             }
 
-            if self.chunk.compare_exchange_weak(chunk, chunk + 1, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            yield_point();
+
+            if self
+                .chunk
+                .compare_exchange_weak(chunk, chunk + 1, self.ordering.swap(), self.ordering.load())
+                .is_ok()
+            {
                 break;
             }
         }
 
-        fence(Ordering::SeqCst);
+        self.held_reads.fetch_add(1, Ordering::Relaxed);
+        self.ordering.fence();
         Ok(())
     }
 
     /// Decrease the read lock counter if we can safely do so.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(iterations), ret, err)
+    )]
     fn unlock_read(&self) -> MMFResult<()> {
+        let mut iterations = 0_usize;
         loop {
-            let chunk = self.chunk.load(Ordering::Acquire);
+            iterations += 1;
+            trace_iterations(iterations);
+            let chunk = self.chunk.load(self.ordering.load());
 
             if !Self::initialized(chunk) {
                 return Err(Error::Uninitialized);
             }
 
             if Self::writelocked(chunk) {
-                // this error code is used in lock_read to indicate you cannot lock it because there is a write lock
-                // however this bit uses it to signal that it's write locked and your lock usage is broken.
-                // should this be two seperate error codes? ditto for other usages of this error code.
-                //
-                // states should probably also use a lock type that is seperate from the high level API since that error
-                // isn't possible there
-                return Err(Error::WriteLocked);
+                // Nobody can hold a read lock while it's write locked, this indicates bad lock usage
+                return Err(Error::NotReadLocked);
             }
 
-            if chunk == 0 {
-                // joel: use a better error code like "NoReaders" or something? indicates bad lock usage
-                return Err(Error::GeneralFailure);
+            if !Self::readlocked(chunk) {
+                // Same here, there is no read lock to release
+                return Err(Error::NoReadersToRelease);
             }
 
-            if self.chunk.compare_exchange_weak(chunk, chunk - 1, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            yield_point();
+
+            if self
+                .chunk
+                .compare_exchange_weak(chunk, chunk - 1, self.ordering.swap(), self.ordering.load())
+                .is_ok()
+            {
                 break;
             }
         }
 
-        fence(Ordering::SeqCst);
+        // Saturate, as this may release a read lock another instance took.
+        _ = self.held_reads.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |held| held.checked_sub(1));
+        self.ordering.fence();
         Ok(())
     }
 
     /// Set the write lock bit to 1 if possible.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(iterations), ret, err)
+    )]
     fn lock_write(&self) -> MMFResult<()> {
+        let mut iterations = 0_usize;
         loop {
-            let chunk = self.chunk.load(Ordering::Acquire);
+            iterations += 1;
+            trace_iterations(iterations);
+            let chunk = self.chunk.load(self.ordering.load());
 
             if !Self::initialized(chunk) {
                 return Err(Error::Uninitialized);
@@ -299,41 +532,257 @@ impl MMFLock for RWLock<'_> {
                 return Err(Error::ReadLocked);
             }
 
+            yield_point();
+
             if self
                 .chunk
-                .compare_exchange_weak(chunk, chunk | Self::WRITE_LOCK_MASK, Ordering::AcqRel, Ordering::Acquire)
+                .compare_exchange_weak(chunk, chunk | Self::WRITE_LOCK_MASK, self.ordering.swap(), self.ordering.load())
                 .is_ok()
             {
                 break;
             }
         }
 
-        fence(Ordering::SeqCst);
+        self.held_write.store(true, Ordering::Relaxed);
+        self.ordering.fence();
         Ok(())
     }
 
     /// Release a write lock if one is being held
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), fields(iterations), ret, err)
+    )]
     fn unlock_write(&self) -> MMFResult<()> {
+        let mut iterations = 0_usize;
         loop {
-            let chunk = self.chunk.load(Ordering::Acquire);
+            iterations += 1;
+            trace_iterations(iterations);
+            let chunk = self.chunk.load(self.ordering.load());
 
             if !Self::initialized(chunk) {
                 return Err(Error::Uninitialized);
             }
 
             if !Self::writelocked(chunk) {
-                // this should also probably be a different error code, indicates bad lock usage
-                return Err(Error::WriteLocked);
+                // There is no write lock to release, this indicates bad lock usage
+                return Err(Error::NotWriteLocked);
             }
 
             if Self::readlocked(chunk) {
-                // this should also probably be a different error code, indicates bad lock usage
+                // Readers snuck in while it was write locked, the lock state is broken beyond what unlocking can fix
                 return Err(Error::ReadLocked);
             }
 
+            yield_point();
+
+            if self
+                .chunk
+                .compare_exchange_weak(chunk, chunk ^ Self::WRITE_LOCK_MASK, self.ordering.swap(), self.ordering.load())
+                .is_ok()
+            {
+                break;
+            }
+        }
+
+        self.held_write.store(false, Ordering::Relaxed);
+        self.ordering.fence();
+        Ok(())
+    }
+
+    /// Very crude implementation of spinning with no backoff.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(lock), fields(tries), ret, err))]
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        let mut tries = 0;
+
+        while match lock.lock_read() {
+            Ok(_) => false,
+            Err(Error::WriteLocked) => true,
+            err => return err,
+        } {
+            tries += 1;
+            trace_tries(tries);
+            if tries >= max_tries {
+                return Err(Error::MaxTriesReached);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Very crude implementation of spinning with no backoff.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(lock), fields(tries), ret, err))]
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        let mut tries = 0;
+
+        while match lock.lock_write() {
+            Ok(_) => false,
+            Err(Error::WriteLocked | Error::ReadLocked) => true,
+            err => return err,
+        } {
+            tries += 1;
+            trace_tries(tries);
+            if tries >= max_tries {
+                return Err(Error::MaxTriesReached);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A sequence lock for the case of one writer and many fast readers.
+///
+/// Writers bump the sequence from even to odd before writing and back to even when done. Readers don't take any lock,
+/// instead they note the sequence before reading and retry if it changed or was odd while they were copying data. This
+/// makes reading wait-free as long as nobody is writing, at the cost of readers having to copy data again when a write
+/// raced them. See [`MemoryMappedFile::read_seqlock`][crate::mmf::MemoryMappedFile::read_seqlock] for the read path.
+///
+/// The first byte is used to track initialization the same way [`RWLock`] does, leaving 24 bits for the sequence.
+/// The sequence wraps around when it runs out of bits, which is harmless unless a reader sleeps through 2^23 writes.
+///
+/// Using this with the regular reading functions of an MMF only ensures no write is in progress when starting the
+/// read. Torn reads are only prevented when reading through `read_seqlock`.
+#[cfg(feature = "impl_lock")]
+#[derive(Debug)]
+pub struct SeqLock<'a> {
+    /// An Atomic reference to the first 4 bytes in the MemoryMappedView.
+    chunk: &'a AtomicU32,
+}
+
+#[cfg(feature = "impl_lock")]
+impl SeqLock<'_> {
+    /// Mask to check if the lock is initialized
+    pub const INITIALIZE_MASK: u32 = INITIALIZE_MASK;
+    /// Mask to get the sequence number
+    pub const SEQUENCE_MASK: u32 = !Self::INITIALIZE_MASK;
+
+    /// Check if this lock has been initialized at all. See [`RWLock`] for how this works.
+    fn initialized(chunk: u32) -> bool {
+        initialized(chunk)
+    }
+
+    /// Check if a writer is currently writing, which is the case when the sequence is odd.
+    fn writing(chunk: u32) -> bool {
+        chunk & 1 == 1
+    }
+
+    /// Bump the sequence by one, wrapping around within the bits available to it.
+    fn bump(chunk: u32) -> u32 {
+        (chunk & Self::INITIALIZE_MASK) | (chunk.wrapping_add(1) & Self::SEQUENCE_MASK)
+    }
+
+    /// Wait until no writer is active and return the sequence to validate the read against.
+    pub fn begin_read(&self) -> MMFResult<u32> {
+        loop {
+            let chunk = self.chunk.load(Ordering::Acquire);
+
+            if !Self::initialized(chunk) {
+                return Err(Error::Uninitialized);
+            }
+
+            if !Self::writing(chunk) {
+                return Ok(chunk);
+            }
+
+            spin_loop();
+        }
+    }
+
+    /// Check if data read since [`begin_read`][Self::begin_read] returned `seq` is free of concurrent writes.
+    pub fn validate_read(&self, seq: u32) -> bool {
+        fence(Ordering::Acquire);
+        self.chunk.load(Ordering::Relaxed) == seq
+    }
+}
+
+#[cfg(feature = "impl_lock")]
+/// Implements the [`MMFLock`] interface in terms of sequence numbers.
+impl MMFLock for SeqLock<'_> {
+    /// Construct a lock from an existing pointer. The same safety concerns apply as for [`RWLock::from_existing`].
+    ///
+    /// # Safety
+    /// See [`RWLock::from_existing`].
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        if pointer.is_null() {
+            panic!("Never, ever pass a null pointer into a lock!")
+        }
+        Self { chunk: AtomicU32::from_ptr(pointer.cast()) }
+    }
+
+    /// Similar to [`Self::from_existing`], except it clears all state and ensures [`Self::initialized`] returns false.
+    ///
+    /// # Safety
+    /// See [`RWLock::from_raw`].
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        if pointer.is_null() {
+            panic!("Never, ever pass a null pointer into a lock!")
+        }
+        let lock = Self { chunk: AtomicU32::from_ptr(pointer.cast()) };
+        lock.chunk.store(Self::INITIALIZE_MASK, Ordering::Release);
+        lock
+    }
+
+    /// Mark this lock as initialized if it isn't yet, starting the sequence at zero.
+    fn set_init(&self) {
+        _ = self.chunk.compare_exchange(Self::INITIALIZE_MASK, 0, Ordering::Release, Ordering::Relaxed);
+    }
+
+    /// Thin wrapper around [`Self::set_init`] that returns self for chaining calls.
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+
+    /// Check if the lock is initialized
+    fn initialized(&self) -> bool {
+        Self::initialized(self.chunk.load(Ordering::Acquire))
+    }
+
+    /// Readers don't lock, this only checks no write is in progress right now.
+    fn lock_read(&self) -> MMFResult<()> {
+        let chunk = self.chunk.load(Ordering::Acquire);
+
+        if !Self::initialized(chunk) {
+            Err(Error::Uninitialized)
+        } else if Self::writing(chunk) {
+            Err(Error::WriteLocked)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Readers don't lock, so there is nothing to release.
+    fn unlock_read(&self) -> MMFResult<()> {
+        if self.initialized() {
+            Ok(())
+        } else {
+            Err(Error::Uninitialized)
+        }
+    }
+
+    /// A writer is active while the sequence is odd. Readers don't lock, so they're never counted.
+    fn write_locked(&self) -> bool {
+        let chunk = self.chunk.load(Ordering::Acquire);
+        Self::initialized(chunk) && Self::writing(chunk)
+    }
+
+    /// Bump the sequence from even to odd, if nobody else is writing.
+    fn lock_write(&self) -> MMFResult<()> {
+        loop {
+            let chunk = self.chunk.load(Ordering::Acquire);
+
+            if !Self::initialized(chunk) {
+                return Err(Error::Uninitialized);
+            }
+
+            if Self::writing(chunk) {
+                return Err(Error::WriteLocked);
+            }
+
             if self
                 .chunk
-                .compare_exchange_weak(chunk, chunk ^ Self::WRITE_LOCK_MASK, Ordering::AcqRel, Ordering::Acquire)
+                .compare_exchange_weak(chunk, Self::bump(chunk), Ordering::AcqRel, Ordering::Acquire)
                 .is_ok()
             {
                 break;
@@ -344,6 +793,34 @@ impl MMFLock for RWLock<'_> {
         Ok(())
     }
 
+    /// Bump the sequence from odd back to even, publishing the write.
+    fn unlock_write(&self) -> MMFResult<()> {
+        fence(Ordering::SeqCst);
+
+        loop {
+            let chunk = self.chunk.load(Ordering::Acquire);
+
+            if !Self::initialized(chunk) {
+                return Err(Error::Uninitialized);
+            }
+
+            if !Self::writing(chunk) {
+                // Same as with `RWLock`, this indicates bad lock usage
+                return Err(Error::NotWriteLocked);
+            }
+
+            if self
+                .chunk
+                .compare_exchange_weak(chunk, Self::bump(chunk), Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Very crude implementation of spinning with no backoff.
     fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
         let mut tries = 0;
@@ -367,6 +844,100 @@ impl MMFLock for RWLock<'_> {
         let mut tries = 0;
 
         while match lock.lock_write() {
+            Ok(_) => false,
+            Err(Error::WriteLocked) => true,
+            err => return err,
+        } {
+            tries += 1;
+            if tries >= max_tries {
+                return Err(Error::MaxTriesReached);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A boxed lock, for picking the [`MMFLock`] to use at runtime rather than being generic over it.
+///
+/// This implements [`MMFLock`] by forwarding to whatever lock is in the box, so `MemoryMappedFile<DynLock>` is the same
+/// type regardless of the lock used. The pointer constructors box an [`RWLock`], and the spinning functions can't reach
+/// the boxed lock's own, so they spin the same crude way [`RWLock`] does. As the box is [`Send`] and [`Sync`], an MMF
+/// using it is too when the `mmf_send` feature is enabled.
+#[cfg(feature = "impl_lock")]
+pub type DynLock = Box<dyn MMFLock + Send + Sync>;
+
+#[cfg(feature = "impl_lock")]
+/// Forwards everything to the boxed lock.
+impl MMFLock for DynLock {
+    /// Forwarded to the boxed lock
+    fn lock_read(&self) -> MMFResult<()> {
+        (**self).lock_read()
+    }
+
+    /// Forwarded to the boxed lock
+    fn unlock_read(&self) -> MMFResult<()> {
+        (**self).unlock_read()
+    }
+
+    /// Forwarded to the boxed lock
+    fn lock_write(&self) -> MMFResult<()> {
+        (**self).lock_write()
+    }
+
+    /// Forwarded to the boxed lock
+    fn unlock_write(&self) -> MMFResult<()> {
+        (**self).unlock_write()
+    }
+
+    /// Forwarded to the boxed lock
+    fn initialized(&self) -> bool {
+        (**self).initialized()
+    }
+
+    /// Forwarded to the boxed lock
+    fn reader_guard_count(&self) -> u32 {
+        (**self).reader_guard_count()
+    }
+
+    /// Forwarded to the boxed lock
+    fn holds_write_lock(&self) -> bool {
+        (**self).holds_write_lock()
+    }
+
+    /// Forwarded to the boxed lock
+    fn write_locked(&self) -> bool {
+        (**self).write_locked()
+    }
+
+    /// Forwarded to the boxed lock
+    fn reader_count(&self) -> u32 {
+        (**self).reader_count()
+    }
+
+    /// Very crude implementation of spinning with no backoff.
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        let mut tries = 0;
+
+        while match (**lock).lock_read() {
+            Ok(_) => false,
+            Err(Error::WriteLocked) => true,
+            err => return err,
+        } {
+            tries += 1;
+            if tries >= max_tries {
+                return Err(Error::MaxTriesReached);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Very crude implementation of spinning with no backoff.
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        let mut tries = 0;
+
+        while match (**lock).lock_write() {
             Ok(_) => false,
             Err(Error::WriteLocked | Error::ReadLocked) => true,
             err => return err,
@@ -379,4 +950,209 @@ impl MMFLock for RWLock<'_> {
 
         Ok(())
     }
+
+    /// Boxes an [`RWLock`] attached to the existing lock state.
+    ///
+    /// # Safety
+    /// See [`RWLock::from_existing`].
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        Box::new(RWLock::from_existing(pointer))
+    }
+
+    /// Boxes a fresh [`RWLock`].
+    ///
+    /// # Safety
+    /// See [`RWLock::from_raw`].
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        Box::new(RWLock::from_raw(pointer))
+    }
+
+    /// Forwarded to the boxed lock
+    fn set_init(&self) {
+        (**self).set_init()
+    }
+
+    /// Thin wrapper around [`Self::set_init`] that returns self for chaining calls.
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+}
+
+/// An [`RWLock`] over an atomic it owns, so the lock logic can be tested without mapping any OS memory.
+///
+/// Every operation forwards to one [`RWLock`] kept for the lifetime of the mock, which means the tests hit the exact
+/// same CAS loops as the real thing, and what this instance took shows up in its
+/// [`reader_guard_count`][MMFLock::reader_guard_count] and [`holds_write_lock`][MMFLock::holds_write_lock].
+/// [`from_existing`][MMFLock::from_existing] attaches to the atomic behind the pointer like the real lock does, so
+/// several mocks can share one state. [`from_raw`][MMFLock::from_raw] makes a fresh lock that owns its atomic instead.
+#[cfg(all(test, feature = "impl_lock"))]
+#[derive(Debug)]
+pub(crate) struct MockLock {
+    /// The real lock, over either the owned atomic or the one this mock attached to.
+    lock: RWLock<'static>,
+    /// Keeps the atomic alive for as long as `lock` points into it. `None` when attached to someone else's.
+    _owned: Option<Arc<AtomicU32>>,
+}
+
+/// Test helpers for poking at the raw state.
+#[cfg(all(test, feature = "impl_lock"))]
+impl MockLock {
+    /// Create a lock with the given raw state, e.g. to test what happens with a full reader count.
+    pub(crate) fn with_state(state: u32) -> Self {
+        let owned = Arc::new(AtomicU32::new(state));
+        // safety: the Arc is stored right next to the lock and never handed out, so the atomic outlives the reference.
+        let chunk = unsafe { &*Arc::as_ptr(&owned) };
+        Self {
+            lock: RWLock {
+                chunk,
+                held_reads: AtomicU32::new(0),
+                held_write: AtomicBool::new(false),
+                ordering: LockOrdering::Strict,
+            },
+            _owned: Some(owned),
+        }
+    }
+
+    /// Get the raw state of the lock.
+    pub(crate) fn state(&self) -> u32 {
+        self.lock.chunk.load(Ordering::Acquire)
+    }
+
+    /// Get a pointer to the atomic, to attach other locks to it.
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.lock.chunk.as_ptr().cast()
+    }
+}
+
+/// Forward everything to [`RWLock`].
+#[cfg(all(test, feature = "impl_lock"))]
+impl MMFLock for MockLock {
+    /// Forwarded to [`RWLock::lock_read`]
+    fn lock_read(&self) -> MMFResult<()> {
+        self.lock.lock_read()
+    }
+
+    /// Forwarded to [`RWLock::unlock_read`]
+    fn unlock_read(&self) -> MMFResult<()> {
+        self.lock.unlock_read()
+    }
+
+    /// Forwarded to [`RWLock::lock_write`]
+    fn lock_write(&self) -> MMFResult<()> {
+        self.lock.lock_write()
+    }
+
+    /// Forwarded to [`RWLock::unlock_write`]
+    fn unlock_write(&self) -> MMFResult<()> {
+        self.lock.unlock_write()
+    }
+
+    /// Forwarded to [`RWLock::initialized`]
+    fn initialized(&self) -> bool {
+        MMFLock::initialized(&self.lock)
+    }
+
+    /// Forwarded to [`RWLock::reader_guard_count`]
+    fn reader_guard_count(&self) -> u32 {
+        self.lock.reader_guard_count()
+    }
+
+    /// Forwarded to [`RWLock::holds_write_lock`]
+    fn holds_write_lock(&self) -> bool {
+        self.lock.holds_write_lock()
+    }
+
+    /// Forwarded to [`RWLock::write_locked`]
+    fn write_locked(&self) -> bool {
+        self.lock.write_locked()
+    }
+
+    /// Forwarded to [`RWLock::reader_count`]
+    fn reader_count(&self) -> u32 {
+        self.lock.reader_count()
+    }
+
+    /// Forwarded to [`RWLock::spin_and_lock_read`]
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        RWLock::spin_and_lock_read(&lock.lock, max_tries)
+    }
+
+    /// Forwarded to [`RWLock::spin_and_lock_write`]
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        RWLock::spin_and_lock_write(&lock.lock, max_tries)
+    }
+
+    /// Attaches to the atomic behind the pointer, see [`RWLock::from_existing`].
+    ///
+    /// # Safety
+    /// On top of the bounds for [`RWLock::from_existing`], the atomic has to outlive the mock.
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        Self { lock: RWLock::from_existing(pointer), _owned: None }
+    }
+
+    /// Creates a new uninitialized lock, the pointer is only checked for null.
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        if pointer.is_null() {
+            panic!("Never, ever pass a null pointer into a lock!")
+        }
+        Self::with_state(RWLock::INITIALIZE_MASK)
+    }
+
+    /// Forwarded to [`RWLock::set_init`]
+    fn set_init(&self) {
+        self.lock.set_init()
+    }
+
+    /// Forwarded to [`RWLock::set_init`]
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+}
+
+#[cfg(all(test, feature = "impl_lock"))]
+thread_local! {
+    /// Hook that tests can install to run code between a lock's `load` and its `compare_exchange`.
+    static YIELD_HOOK: std::cell::RefCell<Option<Box<dyn FnMut()>>> = std::cell::RefCell::new(None);
+}
+
+/// Install a hook that runs on this thread every time a lock operation is about to `compare_exchange`.
+///
+/// This allows tests to deterministically change the lock state in the window where other threads or processes could,
+/// to force the interleavings that are otherwise only found by luck. Pass `None` to remove the hook.
+#[cfg(all(test, feature = "impl_lock"))]
+pub(crate) fn set_yield_hook(hook: Option<Box<dyn FnMut()>>) {
+    YIELD_HOOK.with(|cell| *cell.borrow_mut() = hook);
+}
+
+/// The point between checking the lock state and trying to swap it, where racing lock operations can interfere.
+///
+/// This is a no-op outside of tests. The hook is taken out while it runs, so it may use locks itself without recursing.
+#[cfg(feature = "impl_lock")]
+#[inline(always)]
+fn yield_point() {
+    #[cfg(test)]
+    if let Some(mut hook) = YIELD_HOOK.with(|cell| cell.borrow_mut().take()) {
+        hook();
+        YIELD_HOOK.with(|cell| {
+            cell.borrow_mut().get_or_insert(hook);
+        });
+    }
+}
+
+/// Record how many times a lock operation looped on its CAS on the current span, if `tracing` is enabled.
+#[cfg(feature = "impl_lock")]
+#[inline(always)]
+fn trace_iterations(_iterations: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("iterations", _iterations);
+}
+
+/// Record how many times a spinlock spun on the current span, if `tracing` is enabled.
+#[cfg(feature = "impl_lock")]
+#[inline(always)]
+fn trace_tries(_tries: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("tries", _tries);
 }
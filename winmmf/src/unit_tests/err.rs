@@ -0,0 +1,16 @@
+use crate::err::Error;
+
+#[test]
+pub fn test_code_round_trip() {
+    for code in 0..=u8::MAX {
+        if let Some(err) = Error::from_code(code) {
+            assert_eq!(err.code(), code);
+        }
+    }
+    assert_eq!(Error::from_code(5), Some(Error::MMF_NotFound));
+    assert_eq!(Error::from_code(253), Some(Error::GeneralFailure));
+    assert_eq!(Error::from_code(17), Some(Error::NameTooLong));
+    assert_eq!(Error::from_code(18), None);
+    assert_eq!(Error::from_code(254), None);
+    assert_eq!(Error::OS_Err(windows::core::Error::from_hresult(windows::core::HRESULT(1))).code(), 254);
+}
@@ -1,7 +1,10 @@
-use crate::states::RWLock;
+use crate::states::{RWLock, SeqLock};
 
-use crate::mmf::*;
-use std::num::NonZeroUsize;
+use crate::{err::Error, mmf::*};
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    time::Duration,
+};
 use windows::Win32::Foundation::{self as WFoundation, SetLastError};
 
 #[test]
@@ -84,8 +87,10 @@ pub fn test_no_use_after_close() {
     .expect("2nd open failed");
     unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
 
+    assert!(!file1.is_closed());
     file1.close().expect("Could not close MMF?");
     drop(file2);
+    assert!(file1.is_closed());
     assert!(file1.read(input.len()).is_err());
     drop(file1);
 }
@@ -117,3 +122,1187 @@ pub fn test_no_exist_after_close() {
     drop(file3);
     assert_ne!(&readback, input);
 }
+
+#[test]
+pub fn test_open_missing() {
+    let opened =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "test_open_missing", Namespace::LOCAL, false);
+    assert!(matches!(opened, Err(Error::MMF_NotFound)));
+}
+
+#[test]
+pub fn test_wait_for_init() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_wait_for_init", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let file2 =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "test_wait_for_init", Namespace::LOCAL, true)
+            .expect("2nd open failed");
+    file2.wait_for_init(Duration::from_millis(100)).expect("Lock was never initialized");
+
+    file2.close().expect("Could not close MMF?");
+    assert!(file2.wait_for_init(Duration::from_millis(100)).is_err());
+    drop(file1);
+    drop(file2);
+}
+
+#[test]
+pub fn test_open_all() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(size, "test_open_all.header", Namespace::LOCAL).expect("creation failed");
+    let file2 = MemoryMappedFile::<RWLock>::new(size, "test_open_all.data", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+
+    let opened = open_all::<RWLock>(&[
+        (size, "test_open_all.header", Namespace::LOCAL, true),
+        (size, "test_open_all.data", Namespace::LOCAL, false),
+    ])
+    .expect("Failed to open all");
+    assert_eq!(opened.len(), 2);
+    assert_eq!(opened[1].filename(), "test_open_all.data");
+    drop(opened);
+
+    let missing = open_all::<RWLock>(&[
+        (size, "test_open_all.header", Namespace::LOCAL, true),
+        (size, "test_open_all.index", Namespace::LOCAL, false),
+    ]);
+    assert!(missing.is_err());
+
+    drop(file1);
+    drop(file2);
+}
+
+#[test]
+pub fn test_teardown() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_teardown", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.teardown().expect("Teardown was not clean");
+}
+
+#[test]
+pub fn test_spin_tries() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_spin_tries", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write_spin_tries(input, 8).expect("Failed to write");
+    let readback = file1.read_spin_tries(input.len(), 8).expect("Failed to read");
+    drop(file1);
+    assert_eq!(&readback, input);
+}
+
+#[test]
+pub fn test_open_too_large() {
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_open_too_large", Namespace::LOCAL)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let file2 = MemoryMappedFile::<RWLock>::open(
+        NonZeroUsize::new(1 << 20).unwrap(),
+        "test_open_too_large",
+        Namespace::LOCAL,
+        false,
+    );
+    assert!(file2.is_err());
+    assert!(file1.committed_size().expect("Failed to query size") >= 64);
+    drop(file1);
+}
+
+#[test]
+pub fn test_read_to_small_buf() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_to_small_buf", Namespace::LOCAL)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let mut buf = Vec::with_capacity(8);
+    file1.read_to_buf(&mut buf, 128).expect("Failed to read");
+    assert_eq!(buf.len(), 64);
+    assert_eq!(&buf[..input.len()], input);
+    drop(file1);
+}
+
+#[test]
+pub fn test_read_to_raw_count() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_to_raw_count", Namespace::LOCAL)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let mut buf = [0xFF; 200];
+    assert_eq!(unsafe { file1.read_to_raw(buf.as_mut_ptr(), buf.len()) }, Ok(64));
+    assert_eq!(&buf[..input.len()], input);
+    assert!(buf[64..].iter().all(|&b| b == 0xFF));
+    let sub = file1.subview(8, 16).expect("Failed to slice");
+    assert_eq!(unsafe { sub.read_to_raw(buf.as_mut_ptr(), 32) }, Ok(16));
+}
+
+#[test]
+pub fn test_read_into() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_into", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let mut buf = b"leftovers".to_vec();
+    file1.read_into(&mut buf, 0).expect("Failed to read");
+    assert_eq!(buf.len(), 64);
+    let (ptr, cap) = (buf.as_ptr(), buf.capacity());
+    for _ in 0..8 {
+        file1.read_into(&mut buf, input.len()).expect("Failed to read");
+        assert_eq!(&buf, input);
+    }
+    assert_eq!((buf.as_ptr(), buf.capacity()), (ptr, cap), "a warm buffer was reallocated");
+    drop(file1);
+}
+
+#[test]
+pub fn test_read_seqlock() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<SeqLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_seqlock", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let mut buf = Vec::new();
+    file1.read_seqlock(&mut buf, input.len()).expect("Failed to read");
+    drop(file1);
+    assert_eq!(&buf, input);
+}
+
+#[test]
+pub fn test_read_consistent() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 =
+        MemoryMappedFile::<SeqLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_consistent", Namespace::LOCAL)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    assert_eq!(&file1.read_consistent(input.len(), 3).expect("Failed to read"), input);
+    assert_eq!(file1.read_consistent(0, 0), Err(Error::MaxTriesReached));
+}
+
+#[test]
+pub fn test_copy_from() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_copy_from.src", Namespace::LOCAL)
+        .expect("creation failed");
+    let file2 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_copy_from.dst", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    file2.copy_from(&file1).expect("Failed to copy");
+    assert!(file1.copy_from(&file1).is_err());
+    let readback = file2.read(input.len()).expect("Failed to read");
+    drop(file1);
+    drop(file2);
+    assert_eq!(&readback, input);
+}
+
+#[test]
+pub fn test_subview() {
+    let header = b"HEAD";
+    let body = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_subview", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+
+    let head = file1.subview(0, header.len()).expect("Failed to get the header");
+    let rest = file1.subview(header.len(), 60).expect("Failed to get the body");
+    assert!(file1.subview(8, 60).is_err());
+    head.write(header).expect("Failed to write the header");
+    rest.write(body).expect("Failed to write the body");
+    assert!(head.write(body).is_err());
+
+    let readback = file1.read(header.len() + body.len()).expect("Failed to read");
+    let body_readback = rest.read(body.len()).expect("Failed to read the body");
+    drop(file1);
+    assert_eq!(&readback[..header.len()], header);
+    assert_eq!(&readback[header.len()..], body);
+    assert_eq!(&body_readback, body);
+}
+
+#[test]
+pub fn test_u64_sizes() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    // A view larger than the data is clamped to it.
+    let file1 = MemoryMappedFile::<RWLock>::new_u64(
+        NonZeroU64::new(64).unwrap(),
+        NonZeroUsize::new(1 << 20).unwrap(),
+        "test_u64_sizes",
+        Namespace::LOCAL,
+    )
+    .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.size(), 64);
+    file1.write(input).expect("Failed to write");
+    let file2 = MemoryMappedFile::<RWLock>::open_u64(
+        NonZeroU64::new(64).unwrap(),
+        NonZeroUsize::new(64).unwrap(),
+        "test_u64_sizes",
+        Namespace::LOCAL,
+        true,
+    )
+    .expect("2nd open failed");
+    let readback = file2.read(input.len()).expect("Failed to read");
+    assert_eq!(file2.size(), 64);
+    drop(file1);
+    drop(file2);
+    assert_eq!(&readback, input);
+
+    // Only the view is mapped, the rest of the data is reached through ranged views.
+    let size = 1 << 20;
+    let file3 = MemoryMappedFile::<RWLock>::new_u64(
+        NonZeroU64::new(size).unwrap(),
+        NonZeroUsize::new(64).unwrap(),
+        "test_u64_view",
+        Namespace::LOCAL,
+    )
+    .expect("creation failed");
+    assert_eq!(file3.size(), 64);
+    let tail = MemoryMappedFile::<RWLock>::open_range(
+        "test_u64_view",
+        Namespace::LOCAL,
+        size - 64,
+        NonZeroUsize::new(64).unwrap(),
+        false,
+    )
+    .expect("Failed to open the end of the data");
+    tail.write(input).expect("Failed to write");
+    assert_eq!(&tail.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_debug_metadata() {
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_debug_metadata", Namespace::LOCAL)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let debugged = format!("{file1:?}");
+    let guard = lock_write_all(&[&file1], Duration::from_millis(100)).expect("Failed to lock for writing");
+    let locked = format!("{file1:?}");
+    drop(guard);
+    drop(file1);
+    assert!(debugged.contains("test_debug_metadata"));
+    assert!(debugged.contains("size: 64"));
+    assert!(debugged.contains("write_locked: false, readers: 0"));
+    assert!(locked.contains("write_locked: true"));
+    assert!(!debugged.contains("write_ptr"));
+}
+
+#[test]
+pub fn test_namespace_from_str() {
+    assert!(matches!("local".parse(), Ok(Namespace::LOCAL)));
+    assert!(matches!("Global".parse(), Ok(Namespace::GLOBAL)));
+    assert!(matches!(Namespace::try_from("CUSTOM"), Ok(Namespace::CUSTOM)));
+    assert!("session".parse::<Namespace>().is_err());
+}
+
+#[test]
+pub fn test_namespace_prefix() {
+    const PREFIX: &str = Namespace::GLOBAL.prefix();
+    assert_eq!(PREFIX, GLOBAL_NAMESPACE.as_str());
+    assert_eq!(Namespace::LOCAL.prefix(), LOCAL_NAMESPACE.as_str());
+    assert_eq!(Namespace::CUSTOM.prefix(), "");
+}
+
+#[test]
+pub fn test_read_cstr() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_cstr", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(b"WinMMF\0garbage").expect("Failed to write");
+    assert_eq!(file1.read_cstr().expect("Failed to read"), "WinMMF");
+
+    file1.write(b"Win\xFFMMF\0").expect("Failed to write");
+    assert!(matches!(file1.read_cstr(), Err(Error::InvalidUtf8)));
+    assert_eq!(file1.read_cstr_lossy().expect("Failed to read"), "Win\u{FFFD}MMF");
+
+    file1.write_cstr("Just Works").expect("Failed to write");
+    assert_eq!(file1.read_cstr().expect("Failed to read"), "Just Works");
+    assert!(matches!(file1.write_cstr(&"x".repeat(64)), Err(Error::NotEnoughMemory)));
+    drop(file1);
+}
+
+#[test]
+pub fn test_open_or_create() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::open_or_create(
+        NonZeroUsize::new(64).unwrap(),
+        "test_open_or_create",
+        Namespace::LOCAL,
+    )
+    .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    let file2 = MemoryMappedFile::<RWLock>::open_or_create(
+        NonZeroUsize::new(64).unwrap(),
+        "test_open_or_create",
+        Namespace::LOCAL,
+    )
+    .expect("2nd open failed");
+    let readback = file2.read(input.len()).expect("Failed to read");
+    let larger = MemoryMappedFile::<RWLock>::open_or_create(
+        NonZeroUsize::new(1 << 20).unwrap(),
+        "test_open_or_create",
+        Namespace::LOCAL,
+    );
+    assert!(matches!(larger, Err(Error::NotEnoughMemory)));
+    let too_long =
+        MemoryMappedFile::<RWLock>::open_or_create(NonZeroUsize::new(64).unwrap(), &"n".repeat(58), Namespace::LOCAL);
+    assert!(matches!(too_long, Err(Error::NameTooLong)));
+    drop(file1);
+    drop(file2);
+    assert_eq!(&readback, input);
+}
+
+#[test]
+pub fn test_open_cow() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let local = b"Local changes only";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_open_cow", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    let file2 = MemoryMappedFile::<RWLock>::open_cow(NonZeroUsize::new(64).unwrap(), "test_open_cow", Namespace::LOCAL)
+        .expect("2nd open failed");
+    file2.write(local).expect("Failed to write locally");
+
+    let shared = file1.read(input.len()).expect("Failed to read on 1");
+    let private = file2.read(local.len()).expect("Failed to read on 2");
+    drop(file1);
+    drop(file2);
+    assert_eq!(&shared, input);
+    assert_eq!(&private, local);
+}
+
+#[test]
+pub fn test_watch() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<SeqLock>::new(NonZeroUsize::new(64).unwrap(), "test_watch", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let mut changes = file1.watch(Duration::from_millis(1));
+    assert_eq!(changes.next().expect("No initial data").expect("Failed to read"), vec![0; 64]);
+
+    file1.write(input).expect("Failed to write");
+    let changed = changes.next().expect("No change seen").expect("Failed to read");
+    assert_eq!(&changed[..input.len()], input);
+
+    file1.close().expect("Could not close MMF?");
+    assert!(changes.next().is_none());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn test_serde() {
+    let value = (42u32, String::from("WinMMF Just Works:TM:"), vec![1u8, 2, 3]);
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_serde", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write_serde(&value).expect("Failed to serialize");
+    let read: (u32, String, Vec<u8>) = file1.read_serde().expect("Failed to deserialize");
+    assert_eq!(read, value);
+    assert_eq!(file1.write_serde(&vec![0u8; 64]), Err(Error::NotEnoughMemory));
+}
+
+#[cfg(feature = "unlocked_write")]
+#[test]
+pub fn test_write_unlocked() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_unlocked", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    unsafe { file1.write_unlocked(input) }.expect("Failed to write");
+    assert_eq!(&file1.read(input.len()).expect("Failed to read"), input);
+    assert_eq!(unsafe { file1.write_unlocked(&[0; 65]) }, Err(Error::NotEnoughMemory));
+}
+
+#[test]
+pub fn test_remap() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_remap", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    let handle = WFoundation::HANDLE(file1.handle_value() as _);
+    let mut view = MemoryMappedView::map(handle, 0, 8).expect("Failed to map");
+    view.remap(handle, 0, file1.raw_size()).expect("Failed to remap");
+    let data = unsafe { std::slice::from_raw_parts(view.as_ptr().add(file1.data_offset()), input.len()) };
+    assert_eq!(data, input);
+}
+
+#[test]
+pub fn test_allocation_granularity() {
+    let granularity = system_allocation_granularity();
+    assert!(granularity.is_power_of_two());
+    assert_eq!(align_offset(u64::from(granularity) + 5), u64::from(granularity));
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_granularity", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let handle = WFoundation::HANDLE(file1.handle_value() as _);
+    assert!(matches!(MemoryMappedView::map(handle, 5, 8), Err(Error::UnalignedOffset)));
+}
+
+#[test]
+pub fn test_prefault() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(65536).unwrap(), "test_prefault", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert!(system_page_size().is_power_of_two());
+    file1.prefault().expect("Failed to prefault");
+    file1.write(b"still usable").expect("Failed to write");
+}
+
+#[test]
+pub fn test_lock_in_memory() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_virtual_lock", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.lock_in_memory().expect("Failed to lock pages");
+    file1.unlock_in_memory().expect("Failed to unlock pages");
+    assert!(file1.unlock_in_memory().is_err());
+}
+
+#[test]
+pub fn test_from_io_error() {
+    use std::io::{Error as IoError, ErrorKind};
+    assert_eq!(Error::from(IoError::from(ErrorKind::NotFound)), Error::MMF_NotFound);
+    assert_eq!(Error::from(IoError::from(ErrorKind::PermissionDenied)), Error::AccessDenied);
+    assert_eq!(Error::from(IoError::from(ErrorKind::OutOfMemory)), Error::NotEnoughMemory);
+    assert_eq!(Error::from(IoError::from(ErrorKind::Other)), Error::GeneralFailure);
+}
+
+#[test]
+pub fn test_error_source() {
+    use std::error::Error as _;
+    let ok = Error::from(0i32);
+    assert!(matches!(ok, Error::OS_OK(_)));
+    assert!(ok.source().is_some());
+    assert!(Error::from(1234i32).source().is_some());
+    assert!(Error::GeneralFailure.source().is_none());
+}
+
+#[test]
+pub fn test_try_from_null() {
+    use crate::states::MMFLock;
+    assert!(matches!(unsafe { RWLock::try_from_existing(std::ptr::null_mut()) }, Err(Error::NullPointer)));
+    assert!(matches!(unsafe { SeqLock::try_from_raw(std::ptr::null_mut()) }, Err(Error::NullPointer)));
+    let mut chunk = 0u32;
+    let lock = unsafe { RWLock::try_from_raw((&mut chunk as *mut u32).cast()) }.expect("valid pointer refused");
+    assert!(!lock.initialized());
+}
+
+#[test]
+pub fn test_write_vectored() {
+    let header = b"WinMMF";
+    let body = b" Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_write_vec", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write_vectored(&[header, body]).expect("Failed to write");
+    assert_eq!(&file1.read(header.len() + body.len()).expect("Failed to read"), b"WinMMF Just Works:TM:");
+    assert_eq!(file1.write_vectored(&[&[1; 32], &[2; 33]]), Err(Error::NotEnoughMemory));
+}
+
+#[test]
+pub fn test_read_vectored() {
+    let input = b"WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(16).unwrap(), "test_read_vec", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(&input[..16]).expect("Failed to write");
+    let (mut header, mut body) = ([0; 6], [0; 15]);
+    let read = file1.read_vectored(&mut [&mut header, &mut body]).expect("Failed to read");
+    assert_eq!(read, 16);
+    assert_eq!(&header, b"WinMMF");
+    assert_eq!(&body[..10], b" Just Work");
+}
+
+#[test]
+pub fn test_equals() {
+    let input = b"WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_equals", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    assert!(file1.equals(input).expect("Failed to compare"));
+    assert!(!file1.equals(b"WinMMF Just Breaks").expect("Failed to compare"));
+    assert!(!file1.equals(&[0; 65]).expect("Failed to compare"));
+}
+
+#[test]
+pub fn test_name_too_long() {
+    // "Local\" takes 6 of the 63 bytes.
+    let fits = "n".repeat(57);
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), fits.as_str(), Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.filename_str(), fits);
+
+    let too_long = "n".repeat(58);
+    let created = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), too_long.as_str(), Namespace::LOCAL);
+    assert!(matches!(created, Err(Error::NameTooLong)));
+    let opened = MemoryMappedFile::<RWLock>::open_write(NonZeroUsize::new(64).unwrap(), &too_long, Namespace::LOCAL);
+    assert!(matches!(opened, Err(Error::NameTooLong)));
+}
+
+#[test]
+pub fn test_name_str() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_name_str", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.name_str(), "Local\\test_name_str");
+    assert_eq!(file1.namespace_str(), "Local");
+    assert_eq!(file1.filename_str(), "test_name_str");
+    assert_eq!(file1.fullname(), file1.name_str());
+}
+
+#[test]
+pub fn test_raw_size() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_raw_size", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.data_offset(), LOCK_SIZE);
+    assert_eq!(file1.raw_size(), file1.size() + LOCK_SIZE);
+}
+
+#[test]
+pub fn test_new_large_pages() {
+    let created = MemoryMappedFile::<RWLock>::new_large_pages(
+        NonZeroUsize::new(64).unwrap(),
+        "test_large_pages",
+        Namespace::LOCAL,
+    );
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    // Most test environments don't hold the privilege, so only check we get either a working MMF or a clear error.
+    match created {
+        Ok(file1) => assert!(file1.raw_size() >= 64 + LOCK_SIZE),
+        Err(e) => assert!(matches!(e, Error::AccessDenied | Error::GeneralFailure), "unexpected error: {e:?}"),
+    }
+}
+
+#[test]
+pub fn test_new_reserved() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let size = 1 << 28;
+    let file1 =
+        MemoryMappedFile::<RWLock>::new_reserved(NonZeroUsize::new(size).unwrap(), "test_reserved", Namespace::LOCAL)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.size(), size);
+
+    let offset = size / 2;
+    file1.commit_range(0, input.len()).expect("Failed to commit");
+    file1.commit_range(offset, 4096).expect("Failed to commit");
+    file1.write(input).expect("Failed to write");
+    assert_eq!(&file1.read(input.len()).expect("Failed to read"), input);
+    assert_eq!(file1.subview(offset, 4096).expect("Failed to slice").read(0).expect("Failed to read"), vec![0; 4096]);
+    assert_eq!(file1.commit_range(size - 1, 2), Err(Error::NotEnoughMemory));
+}
+
+#[test]
+pub fn test_copy_fault() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let size = 1 << 20;
+    let file1 =
+        MemoryMappedFile::<RWLock>::new_reserved(NonZeroUsize::new(size).unwrap(), "test_copy_fault", Namespace::LOCAL)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+
+    // Only the page holding the lock is committed, so these touch pages that aren't there.
+    let page = system_page_size() as usize;
+    let past_first_page = file1.subview(page, input.len()).expect("Failed to slice");
+    assert_eq!(past_first_page.read(input.len()), Err(Error::MMF_NotFound));
+    assert_eq!(file1.subview(size / 2, 16).expect("Failed to slice").write(&[1; 16]), Err(Error::MMF_NotFound));
+    // The lock was released both times.
+    file1.commit_range(page, input.len()).expect("Failed to commit");
+    past_first_page.write(input).expect("Failed to write");
+    assert_eq!(&past_first_page.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_prefault_reserved() {
+    let size = 1 << 20;
+    let file1 = MemoryMappedFile::<RWLock>::new_reserved(
+        NonZeroUsize::new(size).unwrap(),
+        "test_prefault_reserved",
+        Namespace::LOCAL,
+    )
+    .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.commit_range(size / 2, 4096).expect("Failed to commit");
+    file1.prefault().expect("Failed to prefault");
+    // The lock was released, and uncommitted pages stayed that way.
+    file1.write(b"still usable").expect("Failed to write");
+    assert_eq!(file1.subview(size / 4, 16).expect("Failed to slice").read(16), Err(Error::MMF_NotFound));
+}
+
+#[test]
+pub fn test_fill() {
+    let file1 =
+        MemoryMappedFile::<RWLock>::new_filled(NonZeroUsize::new(64).unwrap(), "test_fill", Namespace::LOCAL, 0xFF)
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.read(0).expect("Failed to read"), vec![0xFF; 64]);
+    file1.fill(0x2A).expect("Failed to fill");
+    assert_eq!(file1.read(0).expect("Failed to read"), vec![0x2A; 64]);
+}
+
+#[test]
+pub fn test_read_exact_len() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_len", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let read = file1.read(10).expect("Failed to read");
+    assert_eq!(read.len(), 10);
+    assert_eq!(&read, &input[..10]);
+    assert_eq!(file1.read(0).expect("Failed to read").len(), 64);
+    assert_eq!(file1.read(128).expect("Failed to read").len(), 64);
+
+    let mut buf = vec![0xFF; 32];
+    file1.read_to_buf(&mut buf, 10).expect("Failed to read");
+    assert_eq!(&buf, &input[..10]);
+}
+
+#[cfg(feature = "raw_access")]
+#[test]
+pub fn test_raw_access() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_raw_access", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    unsafe { file1.as_mut_slice()[..input.len()].copy_from_slice(input) };
+    assert_eq!(unsafe { file1.as_slice() }.len(), 64);
+    assert_eq!(&unsafe { file1.as_slice() }[..input.len()], input);
+}
+
+#[cfg(feature = "raw_access")]
+#[test]
+pub fn test_as_typed() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_as_typed", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(&[1, 0, 0, 0, 2, 0, 0, 0]).expect("Failed to write");
+    let typed = unsafe { file1.as_typed::<[u32; 2]>() }.expect("Failed to borrow");
+    assert_eq!(*typed, [1, 2]);
+    // The guard holds the read lock, which keeps writers out until it's dropped.
+    assert_eq!(file1.write(&[3]), Err(Error::ReadLocked));
+    drop(typed);
+    file1.write(&[3]).expect("Failed to write");
+    assert_eq!(unsafe { file1.as_typed::<[u8; 65]>() }.map(|v| *v), Err(Error::NotEnoughMemory));
+    assert_eq!(unsafe { file1.as_typed::<u64>() }.map(|v| *v), Err(Error::UnalignedOffset));
+}
+
+#[test]
+pub fn test_new_aligned() {
+    let file1 = MemoryMappedFile::<RWLock>::new_aligned(
+        NonZeroUsize::new(64).unwrap(),
+        "test_new_aligned",
+        Namespace::LOCAL,
+        16,
+    )
+    .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.data_offset(), 16);
+    assert_eq!(file1.raw_size(), 80);
+    file1.write(b"WinMMF").expect("Failed to write");
+
+    let file2 = MemoryMappedFile::<RWLock>::open_aligned(
+        NonZeroUsize::new(64).unwrap(),
+        "test_new_aligned",
+        Namespace::LOCAL,
+        16,
+        false,
+    )
+    .expect("Failed to open");
+    assert_eq!(&file2.read(6).expect("Failed to read"), b"WinMMF");
+    let odd = MemoryMappedFile::<RWLock>::new_aligned(NonZeroUsize::new(64).unwrap(), "test_odd", Namespace::LOCAL, 12);
+    assert!(matches!(odd, Err(Error::UnalignedOffset)));
+}
+
+#[test]
+pub fn test_to_vec() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_to_vec", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let vec = file1.to_vec().expect("Failed to read");
+    assert_eq!(vec.len(), 64);
+    assert_eq!(vec.capacity(), 64);
+    assert_eq!(&vec[..input.len()], input);
+    let boxed = file1.to_boxed_slice().expect("Failed to read");
+    assert_eq!(&boxed[..], &vec[..]);
+    file1.close().expect("Failed to close");
+    assert_eq!(file1.to_vec(), Err(Error::MMF_NotFound));
+}
+
+#[test]
+pub fn test_new_for() {
+    #[allow(dead_code)]
+    #[repr(C, align(8))]
+    struct Header {
+        magic: u32,
+        len: u64,
+    }
+    let file1 =
+        MemoryMappedFile::<RWLock>::new_for::<Header>("test_new_for", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(file1.size(), 16);
+    assert_eq!(file1.data_offset(), 8);
+    let file2 =
+        MemoryMappedFile::<RWLock>::new_for::<u16>("test_new_for.small", Namespace::LOCAL).expect("creation failed");
+    assert_eq!(file2.size(), 2);
+    assert_eq!(file2.data_offset(), LOCK_SIZE);
+    let empty = MemoryMappedFile::<RWLock>::new_for::<()>("test_new_for.empty", Namespace::LOCAL);
+    assert!(matches!(empty, Err(Error::NotEnoughMemory)));
+}
+
+#[test]
+pub fn test_try_write() {
+    use crate::states::MMFLock;
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_try_write", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert!(file1.try_write(input).expect("Failed to write"));
+
+    // Hold a read lock through a second view, as if another process was reading.
+    let handle = WFoundation::HANDLE(file1.handle_value() as _);
+    let view = MemoryMappedView::map(handle, 0, file1.raw_size()).expect("Failed to map");
+    let reader = unsafe { RWLock::from_existing(view.as_ptr()) };
+    reader.lock_read().expect("Failed to lock for reading");
+    assert!(!file1.try_write(input).expect("Contention was an error"));
+    reader.unlock_read().expect("Failed to unlock");
+
+    assert!(file1.try_write(input).expect("Failed to write"));
+    assert_eq!(file1.try_write(&[0; 65]), Err(Error::NotEnoughMemory));
+}
+
+#[test]
+pub fn test_creation_error_mapping() {
+    use windows::{
+        core::HRESULT,
+        Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_NOT_ENOUGH_MEMORY, ERROR_NO_SYSTEM_RESOURCES},
+    };
+    assert_eq!(Error::from(ERROR_NOT_ENOUGH_MEMORY.to_hresult()), Error::NotEnoughMemory);
+    assert_eq!(Error::from(ERROR_NO_SYSTEM_RESOURCES.to_hresult()), Error::NotEnoughMemory);
+    assert_eq!(Error::from(ERROR_ACCESS_DENIED.to_hresult()), Error::AccessDenied);
+    assert_eq!(Error::from(HRESULT(1450)), Error::NotEnoughMemory);
+    assert_eq!(Error::from(HRESULT(5)), Error::AccessDenied);
+}
+
+#[test]
+pub fn test_into_readonly() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_into_ro", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    let file1 = file1.into_readonly();
+    assert!(!file1.is_writable());
+    assert_eq!(file1.write(input), Err(Error::MMF_NotFound));
+    assert_eq!(&file1.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_freeze() {
+    use crate::states::MMFLock;
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let mut file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_freeze", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    file1.freeze().expect("Failed to freeze");
+    assert!(!file1.is_writable());
+    assert_eq!(file1.write(input), Err(Error::MMF_NotFound));
+    assert_eq!(&file1.read(input.len()).expect("Failed to read"), input);
+
+    // The lock is still the one every other handle uses, and the data still changes along with theirs.
+    let file2 = MemoryMappedFile::<RWLock>::open_write(NonZeroUsize::new(64).unwrap(), "test_freeze", Namespace::LOCAL)
+        .expect("Failed to open");
+    let view = MemoryMappedView::map(WFoundation::HANDLE(file2.handle_value() as _), 0, file2.raw_size())
+        .expect("Failed to map");
+    let writer = unsafe { RWLock::from_existing(view.as_ptr()) };
+    writer.lock_write().expect("Failed to lock for writing");
+    assert_eq!(file1.read(0), Err(Error::WriteLocked));
+    writer.unlock_write().expect("Failed to unlock");
+    file2.write(b"WinMMF").expect("Failed to write");
+    assert_eq!(&file1.read(6).expect("Failed to read"), b"WinMMF");
+}
+
+#[test]
+pub fn test_open_read() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_open_read", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let file2 =
+        MemoryMappedFile::<RWLock>::open_read(NonZeroUsize::new(64).unwrap(), "test_open_read", Namespace::LOCAL)
+            .expect("Failed to open");
+    assert_eq!(&file2.read(input.len()).expect("Failed to read"), input);
+    assert_eq!(file2.write(input), Err(Error::MMF_NotFound));
+    // Readers still take the shared lock.
+    let guard = lock_write_all(&[&file1], Duration::from_millis(100)).expect("Failed to lock for writing");
+    assert_eq!(file2.read(0), Err(Error::WriteLocked));
+    drop(guard);
+    file1.write(b"WinMMF").expect("Failed to write");
+    assert_eq!(&file2.read(6).expect("Failed to read"), b"WinMMF");
+}
+
+#[test]
+pub fn test_open_range() {
+    let size = 2 * system_allocation_granularity() as usize;
+    let offset = size / 2 + 10;
+    let input: Vec<u8> = (0..size).map(|i| i as u8).collect();
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(size).unwrap(), "test_open_range", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(&input).expect("Failed to write");
+
+    let range = MemoryMappedFile::<RWLock>::open_range(
+        "test_open_range",
+        Namespace::LOCAL,
+        offset as u64,
+        NonZeroUsize::new(100).unwrap(),
+        false,
+    )
+    .expect("Failed to open range");
+    assert_eq!(range.size(), 100);
+    assert_eq!(range.read(0).expect("Failed to read range"), &input[offset..offset + 100]);
+
+    range.write(b"WinMMF").expect("Failed to write range");
+    assert_eq!(&file1.read(offset + 6).expect("Failed to read")[offset..], b"WinMMF");
+    range.teardown().expect("Failed to tear down");
+}
+
+#[test]
+pub fn test_health_check() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_health", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.health_check().expect("Fresh MMF is unhealthy");
+    file1.close().expect("Failed to close");
+    assert_eq!(file1.health_check(), Err(Error::MMF_NotFound));
+}
+
+#[test]
+pub fn test_drop_releases_reads() {
+    use crate::states::MMFLock;
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_drop_reads", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let file2 =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "test_drop_reads", Namespace::LOCAL, false)
+            .expect("2nd open failed");
+
+    // A misbehaving spinner that takes one read lock too many, which the read never releases.
+    let leaky = |lock: &dyn MMFLock, _| lock.lock_read().and_then(|_| lock.lock_read());
+    file2.read_spin(0, Some(leaky)).expect("Failed to read");
+    assert_eq!(file1.write(input), Err(Error::ReadLocked));
+
+    drop(file2);
+    file1.write(input).expect("Read lock leaked past drop");
+}
+
+#[test]
+pub fn test_drop_releases_write() {
+    use crate::states::MMFLock;
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_drop_write", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let file2 =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "test_drop_write", Namespace::LOCAL, false)
+            .expect("2nd open failed");
+
+    // A misbehaving spinner that takes the write lock, which a read has no business releasing.
+    let leaky = |lock: &dyn MMFLock, _| lock.lock_write();
+    assert!(file2.read_spin(0, Some(leaky)).is_err());
+    assert_eq!(file1.write(input), Err(Error::WriteLocked));
+
+    drop(file2);
+    file1.write(input).expect("Write lock leaked past drop");
+}
+
+#[cfg(feature = "unwind_guard")]
+#[test]
+pub fn test_unwind_releases_lock() {
+    use crate::states::MMFLock;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_unwind_guard", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+
+    // A spinner that takes the lock and then panics, leaving the write to unwind while holding it.
+    let panicky = |lock: &dyn MMFLock, _| -> crate::err::MMFResult<()> {
+        lock.lock_write()?;
+        panic!("spinner blew up");
+    };
+    assert!(catch_unwind(AssertUnwindSafe(|| file1.write_spin(input, Some(panicky)))).is_err());
+    file1.write(input).expect("Write lock leaked past the panic");
+
+    // Same for reads, every read lock taken through the guard gets released.
+    let panicky_read = |lock: &dyn MMFLock, _| -> crate::err::MMFResult<()> {
+        lock.lock_read()?;
+        lock.lock_read()?;
+        panic!("spinner blew up");
+    };
+    assert!(catch_unwind(AssertUnwindSafe(|| file1.read_spin(input.len(), Some(panicky_read)))).is_err());
+    file1.write(input).expect("Read lock leaked past the panic");
+}
+
+#[test]
+pub fn test_is_last_handle() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_last_handle", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let file2 =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "test_last_handle", Namespace::LOCAL, false)
+            .expect("2nd open failed");
+    assert!(!file2.is_last_handle().expect("Failed to check handles"));
+    assert!(!file2.is_closed());
+    file2.write(b"still usable").expect("Failed to write");
+
+    drop(file1);
+    assert!(file2.is_last_handle().expect("Failed to check handles"));
+    assert!(file2.is_closed());
+}
+
+#[test]
+pub fn test_chunk_writer() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<SeqLock>::new(NonZeroUsize::new(16).unwrap(), "test_chunk_writer", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let mut changes = file1.watch(Duration::from_millis(1));
+    changes.next().expect("No initial data").expect("Failed to read");
+
+    let mut writer = file1.chunk_writer();
+    assert_eq!(writer.chunk_capacity(), 12);
+    let mut received = Vec::new();
+    for chunk in input.chunks(writer.chunk_capacity()) {
+        writer.write_chunk(chunk).expect("Failed to write chunk");
+        let data = changes.next().expect("No change seen").expect("Failed to read");
+        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        received.extend_from_slice(&data[4..4 + len]);
+    }
+    assert_eq!(&received, input);
+    assert_eq!(writer.chunks_written(), input.len().div_ceil(12));
+    assert_eq!(writer.write_chunk(&[0; 13]), Err(Error::NotEnoughMemory));
+}
+
+#[test]
+pub fn test_lock_write_all() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_lock_all.b", Namespace::LOCAL)
+        .expect("creation failed");
+    let file2 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_lock_all.a", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+
+    let guard = lock_write_all(&[&file1, &file2], Duration::from_millis(100)).expect("Failed to lock all");
+    guard.write(0, input).expect("Failed to write 1");
+    guard.write(1, b"WinMMF").expect("Failed to write 2");
+    assert_eq!(file1.write(input), Err(Error::WriteLocked));
+    assert!(matches!(lock_write_all(&[&file2], Duration::from_millis(10)).map(drop), Err(Error::MaxTriesReached)));
+    drop(guard);
+    assert_eq!(&file1.read(input.len()).expect("Failed to read 1"), input);
+    assert_eq!(&file2.read(6).expect("Failed to read 2"), b"WinMMF");
+
+    // A readonly MMF can't be locked for writing, and the lock already taken on the other one is released again.
+    let file3 =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "test_lock_all.b", Namespace::LOCAL, true)
+            .expect("2nd open failed");
+    assert!(matches!(lock_write_all(&[&file3, &file2], Duration::from_millis(10)).map(drop), Err(Error::MMF_NotFound)));
+    file2.write(input).expect("Lock leaked past a failed lock_write_all");
+}
+
+#[test]
+pub fn test_read_or_stale() {
+    use crate::states::MMFLock;
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_read_stale", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    let mut buf = Vec::new();
+    assert!(!file1.read_or_stale(input.len(), Duration::from_millis(10), &mut buf).expect("Failed to read"));
+    assert_eq!(&buf, input);
+
+    // Hold a write lock through a second view, as if another process was writing.
+    let handle = WFoundation::HANDLE(file1.handle_value() as _);
+    let view = MemoryMappedView::map(handle, 0, file1.raw_size()).expect("Failed to map");
+    let writer = unsafe { RWLock::from_existing(view.as_ptr()) };
+    writer.lock_write().expect("Failed to lock for writing");
+    assert!(file1.read_or_stale(0, Duration::from_millis(10), &mut buf).expect("Contention was an error"));
+    assert_eq!(&buf, input);
+    writer.unlock_write().expect("Failed to unlock");
+
+    assert!(!file1.read_or_stale(0, Duration::from_millis(10), &mut buf).expect("Failed to read"));
+    assert_eq!(buf.len(), 64);
+}
+
+#[test]
+pub fn test_dyn_lock() {
+    use crate::states::DynLock;
+    /// Only compiles for types that can be shared across threads.
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<DynLock>::new(NonZeroUsize::new(64).unwrap(), "test_dyn_lock", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_send_sync(&file1);
+    file1.write(input).expect("Failed to write");
+    let readback = std::thread::scope(|s| s.spawn(|| file1.read(input.len())).join().unwrap()).expect("Failed to read");
+    assert_eq!(&readback, input);
+}
+
+#[test]
+pub fn test_new_with_lock() {
+    use crate::states::{DynLock, MMFLock};
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<DynLock>::new_with_lock(
+        NonZeroUsize::new(64).unwrap(),
+        "test_with_lock",
+        Namespace::LOCAL,
+        |ptr| unsafe { SeqLock::from_raw(ptr) },
+    )
+    .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    assert_eq!(&file1.read(input.len()).expect("Failed to read"), input);
+
+    // A SeqLock bumps the sequence twice for every write, starting from 0.
+    let handle = WFoundation::HANDLE(file1.handle_value() as _);
+    let view = MemoryMappedView::map(handle, 0, file1.raw_size()).expect("Failed to map");
+    let seq = unsafe { SeqLock::from_existing(view.as_ptr()) };
+    assert_eq!(seq.begin_read().expect("Failed to read the sequence"), 2);
+}
+
+#[test]
+pub fn test_cas_range() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_cas_range", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(b"state: IDLE").expect("Failed to write");
+    assert!(file1.cas_range(7, b"IDLE", b"BUSY").expect("Failed to swap"));
+    assert!(!file1.cas_range(7, b"IDLE", b"BUSY").expect("Failed to compare"));
+    assert_eq!(&file1.read(11).expect("Failed to read"), b"state: BUSY");
+    assert_eq!(file1.cas_range(62, b"\0\0", b"ABC"), Err(Error::NotEnoughMemory));
+}
+
+#[test]
+pub fn test_flags() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_flags", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let file2 = MemoryMappedFile::<RWLock>::open_read(NonZeroUsize::new(64).unwrap(), "test_flags", Namespace::LOCAL)
+        .expect("Failed to open");
+
+    assert!(!file2.get_flag(63).expect("Failed to get flag"));
+    file1.set_flag(63, true).expect("Failed to set flag");
+    assert!(file2.get_flag(63).expect("Failed to get flag"));
+    file1.set_flag(63, false).expect("Failed to clear flag");
+    assert!(!file2.get_flag(63).expect("Failed to get flag"));
+    assert_eq!(file1.set_flag(64, true), Err(Error::NotEnoughMemory));
+    assert_eq!(file2.set_flag(0, true), Err(Error::MMF_NotFound));
+}
+
+#[test]
+pub fn test_wait_flag() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_wait_flag", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert!(!file1.wait_flag(0, Duration::from_millis(10)).expect("Failed to wait"));
+
+    let file2 =
+        MemoryMappedFile::<RWLock>::open_write(NonZeroUsize::new(64).unwrap(), "test_wait_flag", Namespace::LOCAL)
+            .expect("Failed to open");
+    let setter = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        file2.set_flag(0, true).expect("Failed to set flag");
+    });
+    assert!(file1.wait_flag(0, Duration::from_secs(5)).expect("Failed to wait"));
+    setter.join().expect("Setter panicked");
+    assert_eq!(file1.wait_flag(64, Duration::ZERO), Err(Error::NotEnoughMemory));
+}
+
+#[test]
+pub fn test_open_write() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_open_write", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+
+    let file2 =
+        MemoryMappedFile::<RWLock>::open_write(NonZeroUsize::new(64).unwrap(), "test_open_write", Namespace::LOCAL)
+            .expect("Failed to open");
+    file2.write(b"WinMMF").expect("Failed to write");
+    assert_eq!(&file1.read(6).expect("Failed to read"), b"WinMMF");
+    assert!(MemoryMappedFile::<RWLock>::open_write(
+        NonZeroUsize::new(64).unwrap(),
+        "test_open_write_missing",
+        Namespace::LOCAL
+    )
+    .is_err());
+}
+
+#[test]
+pub fn test_mpsc() {
+    let size = NonZeroUsize::new(256).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new(size, "test_mpsc", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let queue = file1.mpsc(16).expect("Failed to set up queue");
+    // 248 bytes after the header fit 10 slots of 24 bytes, rounded down to a power of two.
+    assert_eq!(queue.capacity(), 8);
+    assert_eq!(queue.push(&[0; 17]), Err(Error::NotEnoughMemory));
+
+    let producers: Vec<_> = (0..2u8)
+        .map(|id| {
+            let file =
+                MemoryMappedFile::<RWLock>::open_write(size, "test_mpsc", Namespace::LOCAL).expect("Failed to open");
+            std::thread::spawn(move || {
+                let queue = file.mpsc(16).expect("Failed to set up queue");
+                for i in 0..100u8 {
+                    while !queue.push(&[id, i]).expect("Failed to push") {
+                        std::thread::yield_now();
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    let mut next = [0u8; 2];
+    while next != [100, 100] {
+        if queue.pop(&mut buf).expect("Failed to pop") {
+            let id = buf[0] as usize;
+            // Messages from the same producer come out in the order they went in.
+            assert_eq!(buf, [id as u8, next[id]]);
+            next[id] += 1;
+        }
+    }
+    for producer in producers {
+        producer.join().expect("Producer panicked");
+    }
+    assert!(!queue.pop(&mut buf).expect("Failed to pop"));
+    assert!(file1.into_readonly().mpsc(16).is_err());
+}
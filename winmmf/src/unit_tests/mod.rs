@@ -1,3 +1,9 @@
+mod err;
+#[allow(unused_imports)]
+use err::*;
 mod mmf;
 #[allow(unused_imports)]
 use mmf::*;
+mod states;
+#[allow(unused_imports)]
+use states::*;
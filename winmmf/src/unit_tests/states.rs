@@ -0,0 +1,147 @@
+use crate::err::Error;
+use crate::states::{set_yield_hook, LockOrdering, MMFLock, MockLock, RWLock, SeqLock, INITIALIZE_MASK};
+
+#[test]
+pub fn test_mock_uninitialized() {
+    let lock = MockLock::with_state(RWLock::INITIALIZE_MASK);
+    assert!(!lock.initialized());
+    // The write bit is part of the initialization byte, which doesn't count as held when uninitialized.
+    assert!(!lock.write_locked());
+    assert_eq!(lock.lock_read(), Err(Error::Uninitialized));
+    assert_eq!(lock.lock_write(), Err(Error::Uninitialized));
+    let lock = lock.initialize();
+    assert!(lock.initialized());
+    assert_eq!(lock.state(), 0);
+}
+
+#[test]
+pub fn test_mock_reader_counting() {
+    let lock = MockLock::with_state(0);
+    lock.lock_read().expect("Failed to lock for reading");
+    lock.lock_read().expect("Failed to lock for reading again");
+    assert_eq!(lock.state(), 2);
+    assert_eq!(lock.reader_count(), 2);
+    assert!(!lock.write_locked());
+    assert_eq!(lock.lock_write(), Err(Error::ReadLocked));
+    lock.unlock_read().expect("Failed to unlock");
+    lock.unlock_read().expect("Failed to unlock again");
+    assert_eq!(lock.state(), 0);
+    assert_eq!(lock.unlock_read(), Err(Error::NoReadersToRelease));
+}
+
+#[test]
+pub fn test_mock_max_readers() {
+    let lock = MockLock::with_state(RWLock::READ_LOCK_MASK);
+    assert_eq!(lock.lock_read(), Err(Error::MaxReaders));
+    assert_eq!(MockLock::spin_and_lock_read(&lock, 10), Err(Error::MaxReaders));
+}
+
+#[test]
+pub fn test_mock_write_excludes() {
+    let lock = MockLock::with_state(0);
+    lock.lock_write().expect("Failed to lock for writing");
+    assert_eq!(lock.state(), RWLock::WRITE_LOCK_MASK);
+    assert!(lock.write_locked());
+    assert_eq!(lock.reader_count(), 0);
+    assert_eq!(lock.lock_read(), Err(Error::WriteLocked));
+    assert_eq!(lock.lock_write(), Err(Error::WriteLocked));
+    assert_eq!(MockLock::spin_and_lock_read(&lock, 10), Err(Error::MaxTriesReached));
+    assert_eq!(lock.unlock_read(), Err(Error::NotReadLocked));
+    lock.unlock_write().expect("Failed to unlock");
+    assert_eq!(lock.unlock_write(), Err(Error::NotWriteLocked));
+}
+
+#[test]
+pub fn test_mock_tracks_held() {
+    let lock = MockLock::with_state(0);
+    let other = unsafe { MockLock::from_existing(lock.as_ptr()) };
+    lock.lock_read().expect("Failed to lock for reading");
+    MockLock::spin_and_lock_read(&lock, 10).expect("Failed to spin for reading");
+    other.lock_read().expect("Failed to lock for reading through the attached lock");
+    // Both share the state, but each only counts what was taken through it.
+    assert_eq!(lock.reader_count(), 3);
+    assert_eq!(lock.reader_guard_count(), 2);
+    assert_eq!(other.reader_guard_count(), 1);
+    lock.unlock_read().expect("Failed to unlock");
+    lock.unlock_read().expect("Failed to unlock again");
+    other.unlock_read().expect("Failed to unlock through the attached lock");
+    assert_eq!(lock.reader_guard_count(), 0);
+
+    other.lock_write().expect("Failed to lock for writing");
+    assert!(other.holds_write_lock());
+    assert!(!lock.holds_write_lock());
+    assert!(lock.write_locked());
+    other.unlock_write().expect("Failed to unlock");
+    assert!(!other.holds_write_lock());
+    assert_eq!(lock.state(), 0);
+}
+
+#[test]
+pub fn test_write_races_read() {
+    let lock = MockLock::with_state(0);
+    let chunk = lock.as_ptr();
+    let mut raced = false;
+    // Sneak in a write lock right before the reader's first CAS, as if another process got there first.
+    set_yield_hook(Some(Box::new(move || {
+        if !raced {
+            raced = true;
+            unsafe { RWLock::from_existing(chunk) }.lock_write().expect("Failed to race a write lock");
+        }
+    })));
+    assert_eq!(lock.lock_read(), Err(Error::WriteLocked));
+    set_yield_hook(None);
+    assert_eq!(lock.state(), RWLock::WRITE_LOCK_MASK);
+}
+
+#[test]
+pub fn test_read_races_write() {
+    let lock = MockLock::with_state(0);
+    let chunk = lock.as_ptr();
+    let mut raced = false;
+    set_yield_hook(Some(Box::new(move || {
+        if !raced {
+            raced = true;
+            unsafe { RWLock::from_existing(chunk) }.lock_read().expect("Failed to race a read lock");
+        }
+    })));
+    assert_eq!(lock.lock_write(), Err(Error::ReadLocked));
+    set_yield_hook(None);
+    assert_eq!(lock.state(), 1);
+}
+
+#[test]
+pub fn test_is_initialized_at() {
+    let fresh = MockLock::with_state(RWLock::INITIALIZE_MASK);
+    assert!(!unsafe { RWLock::is_initialized_at(fresh.as_ptr()) });
+    let fresh = fresh.initialize();
+    assert!(unsafe { RWLock::is_initialized_at(fresh.as_ptr()) });
+    assert!(!unsafe { RWLock::is_initialized_at(std::ptr::null()) });
+}
+
+#[test]
+pub fn test_shared_init_convention() {
+    // Uninitialized, fresh, write locked, partially set init byte and a sequence/reader count.
+    for state in [INITIALIZE_MASK, 0, RWLock::WRITE_LOCK_MASK, 0x7F << 24, 5] {
+        let lock = MockLock::with_state(state);
+        let seq = unsafe { SeqLock::from_existing(lock.as_ptr()) };
+        assert_eq!(lock.initialized(), state != INITIALIZE_MASK, "state {state:#010x}");
+        assert_eq!(seq.initialized(), lock.initialized(), "state {state:#010x}");
+    }
+}
+
+#[test]
+pub fn test_relaxed_ordering() {
+    let mock = MockLock::with_state(0);
+    let lock = unsafe { RWLock::from_existing(mock.as_ptr()) };
+    assert_eq!(lock.ordering(), LockOrdering::Strict);
+    // Safety: nothing touches any data guarded by this lock.
+    let lock = unsafe { lock.with_ordering(LockOrdering::Relaxed) };
+    assert_eq!(lock.ordering(), LockOrdering::Relaxed);
+    lock.lock_read().expect("Failed to lock for reading");
+    assert_eq!(lock.lock_write(), Err(Error::ReadLocked));
+    lock.unlock_read().expect("Failed to unlock reading");
+    lock.lock_write().expect("Failed to lock for writing");
+    assert_eq!(mock.state(), RWLock::WRITE_LOCK_MASK);
+    lock.unlock_write().expect("Failed to unlock writing");
+    assert_eq!(mock.state(), 0);
+}